@@ -1,4 +1,5 @@
 use crate::bit::*;
+use crate::zobrist::*;
 
 pub const A1: u8 = 0;
 pub const B1: u8 = 1;
@@ -72,7 +73,29 @@ pub const TERMINATED: u8 = u8::MAX;
 #[derive(Clone)]
 pub struct Board {
     pub bit_board: [u64; 2],
-    pub next_turn: usize
+    pub next_turn: usize,
+    /// Zobrist hash (`hash()`で参照する)
+    ///
+    /// `put_piece_fast`, `pass`によって差分更新される。
+    /// `bit_board`, `next_turn`を直接書き換えた場合は、`update_hash`を呼ぶ必要がある。
+    hash: u64
+}
+
+// `hash`は盤面から決まる値であるため、比較には含めない。
+// (`hash`が古いままの盤面でも、盤面が同じであれば等しいとみなす)
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.bit_board == other.bit_board && self.next_turn == other.next_turn
+    }
+}
+
+impl Eq for Board {}
+
+impl std::hash::Hash for Board {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.bit_board.hash(state);
+        self.next_turn.hash(state);
+    }
 }
 
 pub enum PutPieceErr {
@@ -82,10 +105,7 @@ pub enum PutPieceErr {
 
 impl Default for Board {
     fn default() -> Self {
-        Board {
-            bit_board: [0x0000000810000000u64,0x0000001008000000u64],
-            next_turn: Board::BLACK
-        }
+        Board::from_bit_board([0x0000000810000000u64,0x0000001008000000u64], Board::BLACK)
     }
 }
 
@@ -94,14 +114,56 @@ impl Board {
     pub const BOARD_SIZE: i32 = 8;
     pub const BLACK: usize = 0;
     pub const WHITE: usize = 1;
+    pub const N_SYMMETRIES: u8 = 8;
+    /// 石のない盤面 (黒番)
+    pub const EMPTY: Board = Board { bit_board: [0; 2], next_turn: Board::BLACK, hash: 0 };
 
     pub fn new() -> Self {
         Self::default()
     }
 
+    pub fn from_bit_board(bit_board: [u64; 2], next_turn: usize) -> Self {
+        let mut board = Board { bit_board, next_turn, hash: 0 };
+        board.update_hash();
+        board
+    }
+
     pub fn clear(&mut self) {
-        self.bit_board = [0x0000000810000000u64,0x0000001008000000u64];
-        self.next_turn = Board::BLACK;
+        *self = Board::new();
+    }
+
+    /// 盤面からZobrist hashを計算する。
+    pub fn calc_hash(&self) -> u64
+    {
+        let mut hash = if self.next_turn == Board::WHITE {ZOBRIST.side} else {0};
+        for color in [Board::BLACK, Board::WHITE] {
+            let mut bit = self.bit_board[color];
+            while bit != 0 {
+                hash ^= ZOBRIST.piece[color][bit.trailing_zeros() as usize];
+                bit &= bit - 1;
+            }
+        }
+        hash
+    }
+
+    /// Zobrist hash
+    ///
+    /// 置換表のインデックスと、照合のためのキーとして使用する。
+    #[inline(always)]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// `hash`を盤面から計算し直す。
+    pub fn update_hash(&mut self) {
+        self.hash = self.calc_hash();
+    }
+
+    /// 手番を相手に渡す(パス)。
+    #[inline(always)]
+    pub fn pass(&mut self) {
+        self.next_turn ^= 1;
+        self.hash ^= ZOBRIST.side;
     }
 
     pub fn put_piece_from_coord(&mut self, y: i32, x: i32) -> Result<(), PutPieceErr>
@@ -122,109 +184,7 @@ impl Board {
     #[inline(always)]
     pub fn flip_bit(&self, x: u64) -> u64
     {
-        let p: u64 = self.bit_board[self.next_turn];
-        let o: u64 = self.bit_board[self.next_turn ^ 1];
-        let mut flip = 0u64;
-
-        let maskd = o & 0x7e7e7e7e7e7e7e7e;
-        let mut flip1 =  (x << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        flip1 |=  (flip1 << 1) & maskd;
-        let outflank = p & (flip1 << 1);
-        if outflank == 0 {flip1 = 0};
-        flip |= flip1;
-        
-        // 逆方向
-        let mut flip2 =  (x >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        flip2 |=  (flip2 >> 1) & maskd;
-        let outflank = p & (flip2 >> 1);
-        if outflank == 0 {flip2 = 0};
-        flip |= flip2;
-
-        // 上下
-        let maskd = o & 0xffffffffffffff00;
-        let mut flip1 =  (x << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        flip1 |=  (flip1 << 8) & maskd;
-        let outflank = p & (flip1 << 8);
-        if outflank == 0 {flip1 = 0};
-        flip |= flip1;
-        
-        // 逆方向
-        let mut flip2 =  (x >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        flip2 |=  (flip2 >> 8) & maskd;
-        let outflank = p & (flip2 >> 8);
-        if outflank == 0 {flip2 = 0};
-        flip |= flip2;
-
-        // 斜め
-        let maskd = o & 0x007e7e7e7e7e7e00;
-        let mut flip1 =  (x << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        flip1 |=  (flip1 << 7) & maskd;
-        let outflank = p & (flip1 << 7);
-        if outflank == 0 {flip1 = 0};
-        flip |= flip1;
-        
-        // 逆方向
-        let mut flip2 =  (x >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        flip2 |=  (flip2 >> 7) & maskd;
-        let outflank = p & (flip2 >> 7);
-        if outflank == 0 {flip2 = 0};
-        flip |= flip2;
-
-        // 斜め 2
-        let mut flip1 =  (x << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        flip1 |=  (flip1 << 9) & maskd;
-        let outflank = p & (flip1 << 9);
-        if outflank == 0 {flip1 = 0};
-        flip |= flip1;
-        
-        // 逆方向
-        let mut flip2 =  (x >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        flip2 |=  (flip2 >> 9) & maskd;
-        let outflank = p & (flip2 >> 9);
-        if outflank == 0 {flip2 = 0};
-        flip |= flip2;
-
-        flip
+        flip_bit(self.bit_board[self.next_turn], self.bit_board[self.next_turn ^ 1], x)
     }
 
     #[inline(always)]
@@ -240,8 +200,16 @@ impl Board {
         self.bit_board[0] ^= reverse_bit; // BLACK
         self.bit_board[1] ^= reverse_bit; // WHITE
 
+        // hashの差分更新
+        self.hash ^= ZOBRIST.piece[self.next_turn][put_mask.trailing_zeros() as usize];
+        let mut flipped = reverse_bit;
+        while flipped != 0 {
+            self.hash ^= ZOBRIST.flip[flipped.trailing_zeros() as usize];
+            flipped &= flipped - 1;
+        }
+
         // 次のターンにする
-        self.next_turn ^= 1;
+        self.pass();
     }
 
     #[inline(always)]
@@ -432,53 +400,100 @@ impl Board {
     {
         let mut symmetries = Vec::new();
 
-        for i in 0b0000..0b1000 { // 2^3 = 8 different combinations
-            let mut sym_board = self.clone();
-            if (i & 0b0001) != 0 {
-                sym_board.bit_board[Board::BLACK] = horizontal_mirror(sym_board.bit_board[Board::BLACK]);
-                sym_board.bit_board[Board::WHITE] = horizontal_mirror(sym_board.bit_board[Board::WHITE]);
-            }
-            if (i & 0b0010) != 0 {
-                sym_board.bit_board[Board::BLACK] = vertical_mirror(sym_board.bit_board[Board::BLACK]);
-                sym_board.bit_board[Board::WHITE] = vertical_mirror(sym_board.bit_board[Board::WHITE]);
-            }
-            if (i & 0b0100) != 0 {
-                sym_board.bit_board[Board::BLACK] = transpose(sym_board.bit_board[Board::BLACK]);
-                sym_board.bit_board[Board::WHITE] = transpose(sym_board.bit_board[Board::WHITE]);
-            }
-            symmetries.push(sym_board);
+        for i in 0..Board::N_SYMMETRIES { // 2^3 = 8 different combinations
+            symmetries.push(self.transformed(i));
         }
         symmetries
     }
-    pub fn get_all_rotations(&self) -> Vec<Board>
+
+    /// ビットボードに対称変換を適用する。
+    ///
+    /// `transform` (0 ~ 7) の各bitは、以下の変換を表し、この順に適用される。
+    /// * `0b001` - 左右反転 (`horizontal_mirror`)
+    /// * `0b010` - 上下反転 (`vertical_mirror`)
+    /// * `0b100` - 転置 (`transpose`)
+    #[inline(always)]
+    pub fn transform_bit(mut bit: u64, transform: u8) -> u64
     {
-        let mut rotations = Vec::new();
+        if transform & 0b001 != 0 { bit = horizontal_mirror(bit); }
+        if transform & 0b010 != 0 { bit = vertical_mirror(bit); }
+        if transform & 0b100 != 0 { bit = transpose(bit); }
+        bit
+    }
 
-        let no_rotation = self.clone();
-        rotations.push(no_rotation);
+    /// `transform_bit`の逆変換を適用する。
+    ///
+    /// 変換後の盤面上の着手を、元の盤面上の着手に戻す場合に使用する。
+    #[inline(always)]
+    pub fn inverse_transform_bit(mut bit: u64, transform: u8) -> u64
+    {
+        if transform & 0b100 != 0 { bit = transpose(bit); }
+        if transform & 0b010 != 0 { bit = vertical_mirror(bit); }
+        if transform & 0b001 != 0 { bit = horizontal_mirror(bit); }
+        bit
+    }
+
+    /// 着手位置(0 ~ 63)に対称変換を適用する。
+    #[inline(always)]
+    pub fn transform_move(put_place: u8, transform: u8) -> u8
+    {
+        Board::transform_bit(1u64 << put_place, transform).trailing_zeros() as u8
+    }
+
+    /// 着手位置(0 ~ 63)に`transform_move`の逆変換を適用する。
+    #[inline(always)]
+    pub fn inverse_transform_move(put_place: u8, transform: u8) -> u8
+    {
+        Board::inverse_transform_bit(1u64 << put_place, transform).trailing_zeros() as u8
+    }
 
-        let mut rotate_90_degrees = self.clone();
-        rotate_90_degrees.bit_board[Board::BLACK] = vertical_mirror(rotate_90_degrees.bit_board[Board::BLACK]);
-        rotate_90_degrees.bit_board[Board::WHITE] = vertical_mirror(rotate_90_degrees.bit_board[Board::WHITE]);
-        rotate_90_degrees.bit_board[Board::BLACK] = transpose(rotate_90_degrees.bit_board[Board::BLACK]);
-        rotate_90_degrees.bit_board[Board::WHITE] = transpose(rotate_90_degrees.bit_board[Board::WHITE]);
-        rotations.push(rotate_90_degrees);
+    /// 対称変換を適用した盤面を返す。
+    #[inline(always)]
+    pub fn transformed(&self, transform: u8) -> Board
+    {
+        Board::from_bit_board(
+            [
+                Board::transform_bit(self.bit_board[Board::BLACK], transform),
+                Board::transform_bit(self.bit_board[Board::WHITE], transform)
+            ],
+            self.next_turn
+        )
+    }
 
-        let mut rotate_180_degrees = self.clone();
-        rotate_180_degrees.bit_board[Board::BLACK] = vertical_mirror(rotate_180_degrees.bit_board[Board::BLACK]);
-        rotate_180_degrees.bit_board[Board::WHITE] = vertical_mirror(rotate_180_degrees.bit_board[Board::WHITE]);
-        rotate_180_degrees.bit_board[Board::BLACK] = horizontal_mirror(rotate_180_degrees.bit_board[Board::BLACK]);
-        rotate_180_degrees.bit_board[Board::WHITE] = horizontal_mirror(rotate_180_degrees.bit_board[Board::WHITE]);
-        rotations.push(rotate_180_degrees);
+    /// 盤面の正規形(対称な8つの盤面のうち、最小のもの)と、その変換を求める。
+    ///
+    /// 対称な盤面は同じ正規形を持つため、定石や置換表、学習データの重複除去のキーとして使用できる。
+    /// ヒープ領域の確保は行わない。
+    ///
+    /// # 戻り値
+    /// * `(正規形の盤面, transform)`
+    ///   * `self.transformed(transform)`が正規形となる。
+    ///   * 正規形の盤面上の着手は、`Board::inverse_transform_move`で元の盤面上の着手に戻せる。
+    pub fn canonical(&self) -> (Board, u8)
+    {
+        let mut best = (self.bit_board[Board::BLACK], self.bit_board[Board::WHITE]);
+        let mut best_transform = 0;
+
+        for transform in 1..Board::N_SYMMETRIES {
+            let b = Board::transform_bit(self.bit_board[Board::BLACK], transform);
+            let w = Board::transform_bit(self.bit_board[Board::WHITE], transform);
+            if (b, w) < best {
+                best = (b, w);
+                best_transform = transform;
+            }
+        }
 
-        let mut rotate_270_degrees = self.clone();
-        rotate_270_degrees.bit_board[Board::BLACK] = horizontal_mirror(rotate_270_degrees.bit_board[Board::BLACK]);
-        rotate_270_degrees.bit_board[Board::WHITE] = horizontal_mirror(rotate_270_degrees.bit_board[Board::WHITE]);
-        rotate_270_degrees.bit_board[Board::BLACK] = transpose(rotate_270_degrees.bit_board[Board::BLACK]);
-        rotate_270_degrees.bit_board[Board::WHITE] = transpose(rotate_270_degrees.bit_board[Board::WHITE]);
-        rotations.push(rotate_270_degrees);
+        (Board::from_bit_board([best.0, best.1], self.next_turn), best_transform)
+    }
 
-        rotations
+    pub fn get_all_rotations(&self) -> Vec<Board>
+    {
+        vec![
+            self.clone(),         // no rotation
+            self.transformed(6),  // 90 degrees  (vertical_mirror -> transpose)
+            self.transformed(3),  // 180 degrees (vertical_mirror -> horizontal_mirror)
+            self.transformed(5),  // 270 degrees (horizontal_mirror -> transpose)
+        ]
     }
 
     #[inline(always)]
@@ -543,4 +558,385 @@ impl Board {
         (self.bit_board[0] | self.bit_board[1]).count_zeros() as i32
     }
 
-}
\ No newline at end of file
+    /// 手番側の確定石(今後、裏返されることのない石)を求める。
+    ///
+    /// 全ての確定石を求めるわけではなく、次のいずれかによって確定する石のみを求める。
+    /// * 4方向(横・縦・斜め2方向)の全てで、ラインが石で埋まっている
+    /// * 4方向の全てで、隣接するマスの少なくとも一方が、盤外か自分の確定石である (隅から辺・内側へ伝播する)
+    #[inline(always)]
+    pub fn stable_discs(&self) -> u64 {
+        stable_discs(self.bit_board[self.next_turn], self.bit_board[self.next_turn ^ 1])
+    }
+
+    /// 相手側の確定石を求める。(`stable_discs`を参照)
+    #[inline(always)]
+    pub fn opponent_stable_discs(&self) -> u64 {
+        stable_discs(self.bit_board[self.next_turn ^ 1], self.bit_board[self.next_turn])
+    }
+
+}
+
+/// `p`の石を`x`に置いた時に、裏返る`o`の石を求める。
+///
+/// `Board`を生成せずに、ビットボードのみで着手を計算する場合に用いる。(`Board::flip_bit`を参照)
+#[inline(always)]
+pub fn flip_bit(p: u64, o: u64, x: u64) -> u64
+{
+    let mut flip = 0u64;
+
+    let maskd = o & 0x7e7e7e7e7e7e7e7e;
+    let mut flip1 =  (x << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    flip1 |=  (flip1 << 1) & maskd;
+    let outflank = p & (flip1 << 1);
+    if outflank == 0 {flip1 = 0};
+    flip |= flip1;
+    
+    // 逆方向
+    let mut flip2 =  (x >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    flip2 |=  (flip2 >> 1) & maskd;
+    let outflank = p & (flip2 >> 1);
+    if outflank == 0 {flip2 = 0};
+    flip |= flip2;
+
+    // 上下
+    let maskd = o & 0xffffffffffffff00;
+    let mut flip1 =  (x << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    flip1 |=  (flip1 << 8) & maskd;
+    let outflank = p & (flip1 << 8);
+    if outflank == 0 {flip1 = 0};
+    flip |= flip1;
+    
+    // 逆方向
+    let mut flip2 =  (x >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    flip2 |=  (flip2 >> 8) & maskd;
+    let outflank = p & (flip2 >> 8);
+    if outflank == 0 {flip2 = 0};
+    flip |= flip2;
+
+    // 斜め
+    let maskd = o & 0x007e7e7e7e7e7e00;
+    let mut flip1 =  (x << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    flip1 |=  (flip1 << 7) & maskd;
+    let outflank = p & (flip1 << 7);
+    if outflank == 0 {flip1 = 0};
+    flip |= flip1;
+    
+    // 逆方向
+    let mut flip2 =  (x >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    flip2 |=  (flip2 >> 7) & maskd;
+    let outflank = p & (flip2 >> 7);
+    if outflank == 0 {flip2 = 0};
+    flip |= flip2;
+
+    // 斜め 2
+    let mut flip1 =  (x << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    flip1 |=  (flip1 << 9) & maskd;
+    let outflank = p & (flip1 << 9);
+    if outflank == 0 {flip1 = 0};
+    flip |= flip1;
+    
+    // 逆方向
+    let mut flip2 =  (x >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    flip2 |=  (flip2 >> 9) & maskd;
+    let outflank = p & (flip2 >> 9);
+    if outflank == 0 {flip2 = 0};
+    flip |= flip2;
+
+    flip
+}
+
+const COL_A: u64 = 0x0101010101010101;
+const COL_H: u64 = 0x8080808080808080;
+const ROW_1: u64 = 0x00000000000000ff;
+const ROW_8: u64 = 0xff00000000000000;
+
+/// 斜めのラインのマスク
+/// * `anti == false` : a1-h8方向 (`x - y`が等しいマス)
+/// * `anti == true`  : h1-a8方向 (`x + y`が等しいマス)
+const fn diagonal_masks(anti: bool) -> [u64; 15] {
+    let mut masks = [0u64; 15];
+    let mut sq = 0;
+    while sq < 64 {
+        let (x, y) = (sq % 8, sq / 8);
+        let i = if anti {x + y} else {x + 7 - y};
+        masks[i] |= 1 << sq;
+        sq += 1;
+    }
+    masks
+}
+
+const DIAGONAL_MASKS_9: [u64; 15] = diagonal_masks(false);
+const DIAGONAL_MASKS_7: [u64; 15] = diagonal_masks(true);
+
+/// 石で埋まっているラインに含まれるマスを、方向ごとに求める。
+///
+/// # 戻り値
+/// * `[横, 縦, 斜め(a1-h8方向), 斜め(h1-a8方向)]`
+#[inline(always)]
+fn full_lines(filled: u64) -> [u64; 4] {
+    let mut horizontal = 0;
+    for y in 0..8 {
+        let row = ROW_1 << (8 * y);
+        if filled & row == row {horizontal |= row;}
+    }
+
+    let mut column = filled;
+    column &= column >> 32;
+    column &= column >> 16;
+    column &= column >> 8;
+    let vertical = (column & ROW_1) * COL_A;
+
+    let mut diagonal_9 = 0;
+    for mask in DIAGONAL_MASKS_9 {
+        if filled & mask == mask {diagonal_9 |= mask;}
+    }
+    let mut diagonal_7 = 0;
+    for mask in DIAGONAL_MASKS_7 {
+        if filled & mask == mask {diagonal_7 |= mask;}
+    }
+
+    [horizontal, vertical, diagonal_9, diagonal_7]
+}
+
+/// `p`の確定石を求める。(`Board::stable_discs`を参照)
+fn stable_discs(p: u64, o: u64) -> u64 {
+    let [full_h, full_v, full_9, full_7] = full_lines(p | o);
+
+    let mut stable = p & full_h & full_v & full_9 & full_7;
+    loop {
+        // 各方向について、ラインが埋まっているか、隣接するマスの一方が盤外か確定石であるマス
+        let h = full_h | (stable << 1) | (stable >> 1) | COL_A | COL_H;
+        let v = full_v | (stable << 8) | (stable >> 8) | ROW_1 | ROW_8;
+        let d9 = full_9 | (stable << 9) | (stable >> 9) | COL_A | COL_H | ROW_1 | ROW_8;
+        let d7 = full_7 | (stable << 7) | (stable >> 7) | COL_A | COL_H | ROW_1 | ROW_8;
+        let new_stable = stable | (p & h & v & d9 & d7);
+        if new_stable == stable {
+            return stable;
+        }
+        stable = new_stable;
+    }
+}
+
+/// `random_board`で、局面の生成をやり直す回数の上限
+const RANDOM_BOARD_MAX_TRIES: usize = 1000;
+
+/// ランダムに着手し、空きマスが`n_empties`の、手番側に合法手がある局面を生成する。
+///
+/// 途中で終局した場合は、初期局面からやり直す。
+/// 空きマス数に合法手が無い場合は、パスした局面を返す。
+///
+/// # 戻り値
+/// * `RANDOM_BOARD_MAX_TRIES`回やり直しても生成できない場合(`n_empties`が`0`の場合など)は、`None`
+pub fn random_board<R: rand::Rng>(rng: &mut R, n_empties: i32) -> Option<Board> {
+    'retry: for _ in 0..RANDOM_BOARD_MAX_TRIES {
+        let mut board = Board::new();
+        while board.empties_count() > n_empties {
+            let legal_moves = board.put_able();
+            if legal_moves == 0 {
+                if board.opponent_put_able() == 0 {continue 'retry;}
+                board.pass();
+                continue;
+            }
+            let mut moves = legal_moves;
+            for _ in 0..rng.gen_range(0..legal_moves.count_ones()) {
+                moves &= moves - 1;
+            }
+            board.put_piece_fast(moves & moves.wrapping_neg());
+        }
+        if board.put_able() == 0 {
+            if board.opponent_put_able() == 0 {continue 'retry;}
+            board.pass();
+        }
+        return Some(board);
+    }
+    None
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_able_initial_position() {
+        let board = Board::new();
+        assert_eq!(board.put_able(), (1 << D3) | (1 << C4) | (1 << F5) | (1 << E6));
+        assert_eq!(board.opponent_put_able(), (1 << E3) | (1 << F4) | (1 << C5) | (1 << D6));
+    }
+
+    #[test]
+    fn flip_bit_multiple_directions() {
+        // b1に黒が置く場合
+        // 横: c1, d1 (白) -> e1 (黒) : 裏返る
+        // 斜め: c2 (白) -> d3 (黒) : 裏返る
+        // 縦: b2, b3 (白) -> b4 (空き) : 裏返らない
+        // 斜め: a2 (白) -> 盤外 : 裏返らない
+        let mut board = Board::from_bit_board(
+            [(1 << A1) | (1 << E1) | (1 << D3) | (1 << A3),
+             (1 << C1) | (1 << D1) | (1 << C2) | (1 << B2) | (1 << B3) | (1 << A2)],
+            Board::BLACK
+        );
+        assert_eq!(board.flip_bit(1 << B1), (1 << C1) | (1 << D1) | (1 << C2));
+        board.put_piece_fast(1 << B1);
+        assert_eq!(board.bit_board[Board::WHITE], (1 << B2) | (1 << B3) | (1 << A2));
+        assert_eq!(board.next_turn, Board::WHITE);
+        assert_eq!(board.hash, board.calc_hash());
+    }
+
+    #[test]
+    fn flip_bit_no_wraparound() {
+        // h1の白石を挟む黒石がa2にある(ビット上は隣接)が、盤の端をまたいで裏返してはならない。
+        let board = Board::from_bit_board([1 << A2, (1 << H1) | (1 << G1)], Board::BLACK);
+        assert_eq!(board.flip_bit(1 << F1), 0);
+        assert_eq!(board.put_able() & (1 << F1), 0);
+    }
+
+    #[test]
+    fn stale_hash_does_not_affect_equality() {
+        let mut board = Board::new();
+        board.bit_board = [board.bit_board[1], board.bit_board[0]];
+        assert_ne!(board.hash(), board.calc_hash());
+        let fresh = Board::from_bit_board(board.bit_board, board.next_turn);
+        assert!(board == fresh);
+        board.update_hash();
+        assert_eq!(board.hash(), fresh.hash());
+        assert_eq!(Board::EMPTY.hash(), Board::EMPTY.calc_hash());
+    }
+
+    #[test]
+    fn pass_updates_hash() {
+        let mut board = Board::new();
+        board.pass();
+        assert_eq!(board.next_turn, Board::WHITE);
+        assert_eq!(board.hash, board.calc_hash());
+        board.pass();
+        assert!(board == Board::new());
+    }
+
+    #[test]
+    fn symmetric_boards_have_the_same_canonical_form() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(1);
+
+        for _ in 0..50 {
+            let mut board = Board::new();
+            for _ in 0..rng.gen_range(0..30) {
+                let legal_moves = board.put_able();
+                if legal_moves == 0 {break;}
+                let mut moves = legal_moves;
+                for _ in 0..rng.gen_range(0..legal_moves.count_ones()) {
+                    moves &= moves - 1;
+                }
+                board.put_piece_fast(moves & moves.wrapping_neg());
+            }
+
+            let (canonical, transform) = board.canonical();
+            assert!(canonical == board.transformed(transform));
+            for t in 0..Board::N_SYMMETRIES {
+                let symmetric = board.transformed(t);
+                assert!(symmetric.canonical().0 == canonical, "transform {}", t);
+                // 変換後の盤面の合法手は、元の盤面の合法手を変換したもの
+                assert_eq!(symmetric.put_able(), Board::transform_bit(board.put_able(), t));
+            }
+        }
+    }
+
+    #[test]
+    fn transform_move_round_trip() {
+        for t in 0..Board::N_SYMMETRIES {
+            for put_place in 0..64u8 {
+                let transformed = Board::transform_move(put_place, t);
+                assert!(transformed < 64);
+                assert_eq!(Board::inverse_transform_move(transformed, t), put_place, "transform {}, move {}", t, put_place);
+            }
+        }
+        // 左右反転: a1 -> h1、転置: b1 -> a2
+        assert_eq!(Board::transform_move(A1, 0b001), H1);
+        assert_eq!(Board::transform_move(B1, 0b100), A2);
+    }
+
+    #[test]
+    fn stable_discs_from_corners_and_full_lines() {
+        assert_eq!(Board::new().stable_discs(), 0);
+
+        // 隅から辺に沿って連続する石は確定石
+        // c1は、b1(確定石)に隣接するが、斜めと縦の方向が確定しないため、確定石ではない
+        let board = Board::from_bit_board([(1 << A1) | (1 << B1) | (1 << A2), 1 << C1], Board::BLACK);
+        assert_eq!(board.stable_discs(), (1 << A1) | (1 << B1) | (1 << A2));
+        assert_eq!(board.opponent_stable_discs(), 0);
+
+        // 空きマスがなければ、全ての石が確定石
+        let board = Board::from_bit_board([0x00ff00ff00ff00ff, 0xff00ff00ff00ff00], Board::WHITE);
+        assert_eq!(board.stable_discs(), 0xff00ff00ff00ff00);
+        assert_eq!(board.opponent_stable_discs(), 0x00ff00ff00ff00ff);
+    }
+
+    #[test]
+    fn stable_discs_are_never_flipped() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+
+        for _ in 0..200 {
+            let mut board = Board::new();
+            let mut stable = [0u64; 2];
+            loop {
+                // 一度確定石となった石は、終局まで同じ色のまま
+                for color in [Board::BLACK, Board::WHITE] {
+                    assert_eq!(board.bit_board[color] & stable[color], stable[color]);
+                }
+                stable[board.next_turn] |= board.stable_discs();
+                stable[board.next_turn ^ 1] |= board.opponent_stable_discs();
+
+                let legal_moves = board.put_able();
+                if legal_moves == 0 {
+                    if board.opponent_put_able() == 0 {break;}
+                    board.pass();
+                    continue;
+                }
+                let mut moves = legal_moves;
+                for _ in 0..rng.gen_range(0..legal_moves.count_ones()) {
+                    moves &= moves - 1;
+                }
+                board.put_piece_fast(moves & moves.wrapping_neg());
+            }
+        }
+    }
+}
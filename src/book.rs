@@ -0,0 +1,625 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::collections::hash_map::Entry;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::*;
+use crate::perfect_search::solve_score;
+use crate::solver::*;
+use crate::t_table::*;
+use crate::eval::*;
+use crate::mpc::NO_MPC;
+
+/// 定石に登録されている着手
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct BookMove {
+    /// 着手位置 (0 ~ 63)
+    pub put_place: u8,
+    /// 着手した側から見た、着手後の局面の評価値
+    pub value: i8,
+}
+
+/// 定石の局面
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct BookNode {
+    /// 手番側から見た局面の評価値
+    ///
+    /// 定石の着手がある場合は、それらのnegamax値。
+    /// 葉の場合は、探索による評価値。
+    pub value: i8,
+    /// 葉の評価に使用した探索レベル (`BOOK_LV_EXACT`は完全読み、0は未評価)
+    ///
+    /// MPCを使用して終局まで読んだ場合は、正確な値ではないため、空きマス数とする。
+    pub lv: u8,
+    /// 定石に登録されている着手 (正規形の盤面上の座標)
+    pub moves: Vec<BookMove>,
+}
+
+/// 完全読みにより評価された局面の`BookNode::lv`
+pub const BOOK_LV_EXACT: u8 = 60;
+
+/// 定石の拡張の候補となる葉
+pub struct DeepenCandidate {
+    /// 初期盤面からの着手 (パスは含まない)
+    pub line: Vec<u8>,
+    /// 葉の局面 (`line`を着手した盤面)
+    pub board: Board,
+    /// 初期盤面から葉までの、各局面の評価値と着手の評価値の差の合計
+    pub deviation: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookRecord {
+    bit_board: [u64; 2],
+    next_turn: u8,
+    node: BookNode,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookFile {
+    version: u32,
+    records: Vec<BookRecord>,
+}
+
+/// 定石
+///
+/// 局面は、対称な盤面のうち正規形(`Board::canonical`)をキーとして保存される。
+/// 手番側に合法手がない局面は、パスした後の局面として保存される。
+#[derive(Default)]
+pub struct Book {
+    pub nodes: HashMap<Board, BookNode>,
+}
+
+impl Book {
+    const FILE_VERSION: u32 = 1;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// 定石に保存される局面 (合法手がなく、相手に合法手がある場合はパスした局面)
+    pub fn node_board(board: &Board) -> Board {
+        let mut board = board.clone();
+        if board.put_able() == 0 && board.opponent_put_able() != 0 {
+            board.pass();
+        }
+        board
+    }
+
+    /// 着手後の定石の局面と、着手した側から見た評価値に変換するための符号を求める。
+    ///
+    /// # 戻り値
+    /// * `(着手後の局面, sign)`
+    ///   * 着手した側から見た着手の評価値は、`sign * (着手後の局面の評価値)`
+    pub fn next_node(board: &Board, put_place: u8) -> (Board, i32) {
+        let mut next_board = board.clone();
+        next_board.put_piece_fast(1 << put_place);
+        let node_board = Book::node_board(&next_board);
+        let sign = if node_board.next_turn == board.next_turn {1} else {-1};
+        (node_board, sign)
+    }
+
+    /// 棋譜を定石に追加する。
+    ///
+    /// # 引数
+    /// * `moves` - 初期盤面からの着手位置の列 (パスは含まない)
+    /// * `max_depth` - 定石に追加する手数
+    ///
+    /// # 戻り値
+    /// * 棋譜に不正な着手が含まれていた場合は`false` (不正な着手の直前までは追加される)
+    pub fn add_line(&mut self, moves: &[u8], max_depth: i32) -> bool {
+        let mut board = Board::new();
+
+        for &put_place in moves.iter().take(max_depth.max(0) as usize) {
+            board = Book::node_board(&board);
+            if put_place >= 64 || board.put_able() & (1 << put_place) == 0 {
+                self.nodes.entry(board.canonical().0).or_default();
+                return false;
+            }
+
+            let (key, transform) = board.canonical();
+            let node = self.nodes.entry(key).or_default();
+            let canonical_put_place = Board::transform_move(put_place, transform);
+            if !node.moves.iter().any(|m| m.put_place == canonical_put_place) {
+                node.moves.push(BookMove { put_place: canonical_put_place, value: 0 });
+            }
+
+            board.put_piece_fast(1 << put_place);
+        }
+
+        self.nodes.entry(Book::node_board(&board).canonical().0).or_default();
+        true
+    }
+
+    /// 局面を評価する。
+    ///
+    /// 空きマスが`exact_empties`以下の場合は終局まで読み、それ以外は深さ`lv`の探索を行う。
+    ///
+    /// # 戻り値
+    /// * `(評価値, 探索レベル)`
+    ///   * 探索レベルは、`selectivity_lv`が`NO_MPC`で終局まで読んだ場合のみ`BOOK_LV_EXACT`
+    pub fn evaluate(board: &Board, lv: i32, exact_empties: i32, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> (i8, u8) {
+        if board.put_able() == 0 {
+            if board.opponent_put_able() == 0 {
+                return (solve_score(board) as i8, BOOK_LV_EXACT);
+            }
+            let mut passed_board = board.clone();
+            passed_board.pass();
+            let (value, lv) = Book::evaluate(&passed_board, lv, exact_empties, selectivity_lv, t_table, evaluator);
+            return (-value, lv);
+        }
+
+        let result =
+            if board.empties_count() <= exact_empties {
+                let result_lv = if selectivity_lv == NO_MPC {BOOK_LV_EXACT} else {board.empties_count() as u8};
+                perfect_solver(board, false, selectivity_lv, t_table, evaluator).map(|r| (r.eval as i8, result_lv))
+            } else {
+                eval_solver(board, lv, selectivity_lv, false, t_table, evaluator).map(|r| (r.eval as i8, lv as u8))
+            };
+
+        match result {
+            Ok(r) => r,
+            Err(_) => unreachable!()
+        }
+    }
+
+    /// 未評価の葉を、探索により評価する。
+    ///
+    /// # 戻り値
+    /// * 評価した葉の数
+    pub fn evaluate_leaves(&mut self, lv: i32, exact_empties: i32, selectivity_lv: i32, print_log: bool, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> usize {
+        let leaves: Vec<Board> = self.nodes.iter()
+            .filter(|(_, node)| node.moves.is_empty() && node.lv == 0)
+            .map(|(board, _)| board.clone())
+            .collect();
+
+        for (i, board) in leaves.iter().enumerate() {
+            let (value, lv) = Book::evaluate(board, lv, exact_empties, selectivity_lv, t_table, evaluator);
+            let node = self.nodes.get_mut(board).unwrap();
+            node.value = value;
+            node.lv = lv;
+            if print_log && (i + 1) % 1000 == 0 {
+                println!("evaluated leaves: {} / {}", i + 1, leaves.len());
+            }
+        }
+
+        leaves.len()
+    }
+
+    /// 葉の評価値から、全ての局面と着手の評価値をnegamax法で計算し直す。
+    pub fn negamax(&mut self) {
+        let mut done = HashSet::new();
+        let keys: Vec<Board> = self.nodes.keys().cloned().collect();
+        for key in keys.iter() {
+            self.negamax_node(key, &mut done);
+        }
+    }
+
+    fn negamax_node(&mut self, key: &Board, done: &mut HashSet<Board>) -> Option<i32> {
+        let node = self.nodes.get(key)?;
+        if node.moves.is_empty() || done.contains(key) {
+            return Some(node.value as i32);
+        }
+        done.insert(key.clone());
+
+        let put_places: Vec<u8> = node.moves.iter().map(|m| m.put_place).collect();
+        let mut moves = Vec::with_capacity(put_places.len());
+        for put_place in put_places {
+            let (next_board, sign) = Book::next_node(key, put_place);
+            if let Some(value) = self.negamax_node(&next_board.canonical().0, done) {
+                moves.push(BookMove { put_place, value: (sign * value) as i8 });
+            }
+        }
+
+        let node = self.nodes.get_mut(key).unwrap();
+        if let Some(best) = moves.iter().map(|m| m.value).max() {
+            node.value = best;
+        }
+        node.moves = moves;
+        Some(node.value as i32)
+    }
+
+    /// 定石を拡張する葉を、優先度の高い順に`n`個選ぶ。
+    ///
+    /// 葉の優先度は、最善の評価値から、初期盤面から葉までの着手の偏差(最善手との評価値の差)の合計を引いた値。
+    /// すなわち、双方が最善に近い手を打ち続けた手順の葉ほど優先される。
+    /// 偏差が等しい場合は、手数の少ない葉が優先される。
+    ///
+    /// # 引数
+    /// * `n` - 選ぶ葉の数
+    /// * `max_deviation` - 偏差の合計の上限
+    ///
+    /// # 注記
+    /// * 完全読みで評価された葉と、終局した葉は選ばれない。
+    pub fn deepen_candidates(&self, n: usize, max_deviation: i32) -> Vec<DeepenCandidate> {
+        let mut candidates = Vec::new();
+        let mut settled = HashSet::new();
+        let mut entries = vec![(Book::node_board(&Board::new()), Vec::new())];
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, 0, 0)));
+
+        // 偏差は非負のため、偏差の小さい順に局面を確定させる (ダイクストラ法)
+        while let Some(Reverse((deviation, _, index))) = queue.pop() {
+            let (board, line) = entries[index].clone();
+            let (key, transform) = board.canonical();
+            if !settled.insert(key.clone()) {
+                continue;
+            }
+            let Some(node) = self.nodes.get(&key) else {continue};
+
+            if node.moves.is_empty() {
+                if node.lv != BOOK_LV_EXACT && board.put_able() != 0 {
+                    candidates.push(DeepenCandidate { line, board, deviation });
+                    if candidates.len() >= n {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            for m in node.moves.iter() {
+                let child_deviation = deviation + (node.value as i32 - m.value as i32).max(0);
+                if child_deviation > max_deviation {
+                    continue;
+                }
+                let put_place = Board::inverse_transform_move(m.put_place, transform);
+                let mut child_line = line.clone();
+                child_line.push(put_place);
+                queue.push(Reverse((child_deviation, child_line.len(), entries.len())));
+                entries.push((Book::next_node(&board, put_place).0, child_line));
+            }
+        }
+
+        candidates
+    }
+
+    /// 葉の全ての合法手を定石に追加し、着手後の局面を探索により評価する。
+    ///
+    /// 評価値は更新されないため、拡張した後に`negamax`を呼び出す必要がある。
+    ///
+    /// # 戻り値
+    /// * 新たに追加された局面の数
+    pub fn expand(&mut self, board: &Board, lv: i32, exact_empties: i32, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> usize {
+        let board = Book::node_board(board);
+        let (key, transform) = board.canonical();
+        let mut n_added = 0;
+        let mut moves = Vec::new();
+
+        let mut legal_moves = board.put_able();
+        while legal_moves != 0 {
+            let put_place = legal_moves.trailing_zeros() as u8;
+            legal_moves &= legal_moves - 1;
+
+            let (next_board, _) = Book::next_node(&board, put_place);
+            if let Entry::Vacant(entry) = self.nodes.entry(next_board.canonical().0) {
+                let (value, lv) = Book::evaluate(&next_board, lv, exact_empties, selectivity_lv, t_table, evaluator);
+                entry.insert(BookNode { value, lv, moves: Vec::new() });
+                n_added += 1;
+            }
+            moves.push(BookMove { put_place: Board::transform_move(put_place, transform), value: 0 });
+        }
+
+        let node = self.nodes.entry(key).or_default();
+        for m in moves {
+            if !node.moves.iter().any(|x| x.put_place == m.put_place) {
+                node.moves.push(m);
+            }
+        }
+        n_added
+    }
+
+    /// 盤面に対する定石の着手を、評価値の高い順に取得する。
+    ///
+    /// # 戻り値
+    /// * 着手は、`board`上の座標に変換される。
+    /// * 盤面が定石にない場合や、定石の着手がない場合は`None`
+    pub fn probe(&self, board: &Board) -> Option<Vec<BookMove>> {
+        let (key, transform) = Book::node_board(board).canonical();
+        let node = self.nodes.get(&key)?;
+        if node.moves.is_empty() || board.put_able() == 0 {
+            return None;
+        }
+
+        let mut moves: Vec<BookMove> = node.moves.iter()
+            .map(|m| BookMove { put_place: Board::inverse_transform_move(m.put_place, transform), value: m.value })
+            .collect();
+        moves.sort_by_key(|m| std::cmp::Reverse(m.value));
+        Some(moves)
+    }
+
+    /// 定石を、バイナリ形式でファイルに書き出す。
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut records: Vec<BookRecord> = self.nodes.iter()
+            .map(|(board, node)| BookRecord {
+                bit_board: board.bit_board,
+                next_turn: board.next_turn as u8,
+                node: node.clone(),
+            })
+            .collect();
+        records.sort_by_key(|r| (r.bit_board, r.next_turn));
+
+        let book_file = BookFile { version: Book::FILE_VERSION, records };
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, &book_file).map_err(io::Error::other)
+    }
+
+    /// `write_file`で書き出した定石を読み込む。
+    pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<Book> {
+        let reader = BufReader::new(File::open(path)?);
+        let book_file: BookFile = bincode::deserialize_from(reader)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if book_file.version != Book::FILE_VERSION {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported book version: {}", book_file.version)));
+        }
+
+        let nodes = book_file.records.into_iter()
+            .map(|r| (Board::from_bit_board(r.bit_board, r.next_turn as usize), r.node))
+            .collect();
+        Ok(Book { nodes })
+    }
+}
+
+/// 着手位置を表す文字列 (例: `"f5"`) を、着手位置(0 ~ 63)に変換する。
+pub fn str_to_put_place(s: &str) -> Option<u8> {
+    let mut chars = s.chars();
+    let x = chars.next()?.to_ascii_lowercase();
+    let y = chars.next()?;
+    if !('a'..='h').contains(&x) || !('1'..='8').contains(&y) {
+        return None;
+    }
+    Some((y as u8 - b'1') * 8 + (x as u8 - b'a'))
+}
+
+/// 棋譜の文字列 (例: `"f5d6c3d3c4"`) を、着手位置の列に変換する。
+pub fn parse_transcript(s: &str) -> Option<Vec<u8>> {
+    let s: Vec<char> = s.chars().filter(|c| !c.is_whitespace()).collect();
+    if s.is_empty() || !s.len().is_multiple_of(2) {
+        return None;
+    }
+    s.chunks(2)
+        .map(|c| str_to_put_place(&c.iter().collect::<String>()))
+        .collect()
+}
+
+/// 1行に1局の棋譜が書かれたテキスト(Egaroucidの自己対戦の棋譜など)を読み込む。
+pub fn parse_transcripts(input: &str) -> Vec<Vec<u8>> {
+    input.lines().filter_map(parse_transcript).collect()
+}
+
+/// GGF形式の棋譜を読み込む。
+///
+/// 初期盤面(`BO`)が通常の初期盤面でない対局は読み込まない。
+pub fn parse_ggf(input: &str) -> Vec<Vec<u8>> {
+    const STANDARD_BOARD: &str = "8 ---------------------------O*------*O--------------------------- *";
+
+    let mut games = Vec::new();
+    for game in input.split("(;").skip(1) {
+        let game = game.split(";)").next().unwrap_or("");
+        let mut moves = Vec::new();
+        let mut valid = true;
+
+        let mut rest = game;
+        while let Some(open) = rest.find('[') {
+            let tag = rest[..open].trim();
+            let Some(close) = rest[open..].find(']') else {
+                valid = false;
+                break;
+            };
+            let value = &rest[open + 1..open + close];
+            rest = &rest[open + close + 1..];
+
+            match tag {
+                "BO" if value.trim() != STANDARD_BOARD => valid = false,
+                "B" | "W" => {
+                    let m = value.split('/').next().unwrap_or("").trim();
+                    if m.eq_ignore_ascii_case("pa") {
+                        continue;
+                    }
+                    match str_to_put_place(m) {
+                        Some(put_place) => moves.push(put_place),
+                        None => valid = false
+                    }
+                },
+                _ => ()
+            }
+        }
+
+        if valid && !moves.is_empty() {
+            games.push(moves);
+        }
+    }
+    games
+}
+
+/// WTHOR形式(`.wtb`)の棋譜を読み込む。
+///
+/// 着手は`10 * 行 + 列`(行, 列は`1 ~ 8`)で表され、`0`は棋譜の終わりを表す。
+///
+/// # 戻り値
+/// * 着手の値が不正な場合は、`io::ErrorKind::InvalidData`のエラー
+pub fn parse_wthor(input: &[u8]) -> io::Result<Vec<Vec<u8>>> {
+    const HEADER_SIZE: usize = 16;
+    const RECORD_SIZE: usize = 68;
+    const RECORD_INFO_SIZE: usize = 8;
+
+    if input.len() < HEADER_SIZE {
+        return Ok(Vec::new());
+    }
+
+    input[HEADER_SIZE..].chunks_exact(RECORD_SIZE).enumerate()
+        .map(|(i, record)| {
+            record[RECORD_INFO_SIZE..].iter()
+                .take_while(|&&m| m != 0)
+                .map(|&m| {
+                    let (row, col) = (m / 10, m % 10);
+                    if !(1..=8).contains(&row) || !(1..=8).contains(&col) {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("game {}: invalid move {}", i + 1, m)));
+                    }
+                    Ok((row - 1) * 8 + (col - 1))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// 棋譜ファイルを読み込む。
+///
+/// 拡張子が`.ggf`の場合はGGF形式、`.wtb`の場合はWTHOR形式、それ以外は1行1局の棋譜として読み込む。
+pub fn read_game_records<P: AsRef<Path>>(path: P) -> io::Result<Vec<Vec<u8>>> {
+    let extension = path.as_ref().extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "wtb" => parse_wthor(&fs::read(path)?),
+        "ggf" => Ok(parse_ggf(&fs::read_to_string(path)?)),
+        _     => Ok(parse_transcripts(&fs::read_to_string(path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_game_records() {
+        assert_eq!(parse_transcript("f5d6C3"), Some(vec![F5, D6, C3]));
+        assert_eq!(parse_transcript("f5d"), None);
+        assert_eq!(parse_transcript("f5i9"), None);
+
+        let ggf = "(;GM[Othello]PC[NEC]PB[a]PW[b]TY[8]\
+            BO[8 ---------------------------O*------*O--------------------------- *]\
+            B[f5//1.2]W[d6]B[PA]W[c3];)\
+            (;GM[Othello]TY[8]BO[8 -------------------------------------------------------------O*O *]B[a1];)";
+        assert_eq!(parse_ggf(ggf), vec![vec![F5, D6, C3]]);
+
+        // WTHOR: 16バイトのヘッダと、8バイトの対局情報 + 60バイトの着手 (10 * 行 + 列)
+        let mut wtb = vec![0u8; 16 + 68];
+        wtb[16 + 8] = 56;
+        wtb[16 + 9] = 64;
+        assert_eq!(parse_wthor(&wtb).unwrap(), vec![vec![F5, D6]]);
+        // 行, 列が 1 ~ 8 の範囲外の着手は、エラー
+        for m in [9, 50, 90, 255] {
+            wtb[16 + 10] = m;
+            assert!(parse_wthor(&wtb).is_err(), "{}", m);
+        }
+    }
+
+    #[test]
+    fn symmetric_lines_share_nodes() {
+        let mut book = Book::new();
+        assert!(book.add_line(&parse_transcript("f5d6").unwrap(), 60));
+        // 初期盤面, f5, f5d6
+        assert_eq!(book.len(), 3);
+        // e6f4 は f5d6 と対称
+        assert!(book.add_line(&parse_transcript("e6f4").unwrap(), 60));
+        assert_eq!(book.len(), 3);
+        // 不正な着手
+        assert!(!book.add_line(&parse_transcript("f5f5").unwrap(), 60));
+    }
+
+    #[test]
+    fn negamax_and_probe() {
+        let mut book = Book::new();
+        book.add_line(&parse_transcript("f5d6").unwrap(), 60);
+        book.add_line(&parse_transcript("f5f6").unwrap(), 60);
+
+        let mut set_leaf = |transcript: &str, value: i8| {
+            let mut board = Board::new();
+            for put_place in parse_transcript(transcript).unwrap() {
+                board.put_piece_fast(1 << put_place);
+            }
+            book.nodes.get_mut(&board.canonical().0).unwrap().value = value;
+        };
+        set_leaf("f5d6", 3);
+        set_leaf("f5f6", -2);
+        book.negamax();
+
+        // c4 は f5 と対称
+        let mut board = Board::new();
+        board.put_piece_fast(1 << C4);
+        let moves = book.probe(&board).unwrap();
+        assert_eq!(moves.iter().map(|m| m.value).collect::<Vec<_>>(), vec![2, -3]);
+        for m in moves.iter() {
+            assert_ne!(board.put_able() & (1 << m.put_place), 0);
+        }
+        // f5f6 と対称な c4c3 が最善
+        assert_eq!(moves[0].put_place, C3);
+
+        let moves = book.probe(&Board::new()).unwrap();
+        assert_eq!(moves.len(), 1);
+        assert_eq!(moves[0].value, -2);
+
+        let path = std::env::temp_dir().join("deft_reversi_engine_book_test.bin");
+        book.write_file(&path).unwrap();
+        let loaded = Book::read_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), book.len());
+        assert_eq!(loaded.probe(&board).unwrap()[0].put_place, C3);
+    }
+
+    #[test]
+    fn deepen_candidates_and_expand() {
+        let mut book = Book::new();
+        book.add_line(&parse_transcript("f5d6").unwrap(), 60);
+        book.add_line(&parse_transcript("f5f6").unwrap(), 60);
+        for (transcript, value) in [("f5d6", 3), ("f5f6", -2)] {
+            let mut board = Board::new();
+            for put_place in parse_transcript(transcript).unwrap() {
+                board.put_piece_fast(1 << put_place);
+            }
+            let node = book.nodes.get_mut(&board.canonical().0).unwrap();
+            node.value = value;
+            node.lv = 1;
+        }
+        book.negamax();
+
+        // f5f6 は最善の手順 (偏差 0)、f5d6 は最善より5石悪い手順
+        let candidates = book.deepen_candidates(2, i32::MAX);
+        assert_eq!(candidates.iter().map(|c| c.deviation).collect::<Vec<_>>(), vec![0, 5]);
+        assert_eq!(candidates[0].line.len(), 2);
+        assert_eq!(book.deepen_candidates(2, 4).len(), 1);
+
+        let board = candidates[0].board.clone();
+        let n_nodes = book.len();
+        let n_added = book.expand(&board, 1, 1, NO_MPC, &mut TranspositionTable::new(), &mut Evaluator::new());
+        assert!(n_added > 0);
+        assert_eq!(book.len(), n_nodes + n_added);
+        assert_eq!(book.probe(&board).unwrap().len(), board.put_able().count_ones() as usize);
+
+        // 拡張した葉は候補にならない
+        book.negamax();
+        let candidates = book.deepen_candidates(100, i32::MAX);
+        assert!(candidates.iter().all(|c| c.board != board));
+        assert!(candidates.iter().all(|c| c.deviation >= 0));
+    }
+
+    #[test]
+    fn only_exact_solves_are_labeled_exact() {
+        use rand::SeedableRng;
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+        let board = random_board(&mut rng, 10).unwrap();
+        let mut evaluator = Evaluator::new();
+        let mut evaluate = |lv, exact_empties, selectivity_lv| {
+            Book::evaluate(&board, lv, exact_empties, selectivity_lv, &mut TranspositionTable::new(), &mut evaluator).1
+        };
+
+        assert_eq!(evaluate(4, 10, NO_MPC), BOOK_LV_EXACT);
+        // MPCを使用した終局までの探索は、正確な値として扱わない
+        assert_eq!(evaluate(4, 10, 3), 10);
+        // 終局まで読むかは、`lv`ではなく`exact_empties`で決まる
+        assert_eq!(evaluate(6, 8, NO_MPC), 6);
+    }
+}
@@ -0,0 +1,243 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::time;
+
+use crate::board::*;
+use crate::book::*;
+use crate::t_table::*;
+use crate::eval::*;
+use crate::mpc::SELECTIVITY;
+
+/// 棋譜ファイルから定石を作成する。
+///
+/// 各棋譜の`max_depth`手目までを定石に追加し、葉を`lv`の探索(空きマスが`exact_empties`以下の場合は終局までの探索)で評価した後、
+/// negamax法で評価値を計算する。
+pub fn build_book(files: &[String], max_depth: i32, lv: i32, exact_empties: i32, selectivity_lv: i32) -> Result<Book, String> {
+    let mut book = Book::new();
+    let mut n_games = 0;
+    let mut n_invalid_games = 0;
+
+    for file in files.iter() {
+        let games = read_game_records(file).map_err(|e| format!("{}: {}", file, e))?;
+        for game in games.iter() {
+            if !book.add_line(game, max_depth) {
+                n_invalid_games += 1;
+            }
+        }
+        n_games += games.len();
+        println!("{}: {} games", file, games.len());
+    }
+    println!("games: {} (invalid: {}), positions: {}", n_games, n_invalid_games, book.len());
+
+    let mut evaluator = Evaluator::read_file().map_err(|e| e.to_string())?;
+    let mut t_table = TranspositionTable::new();
+    let now = time::Instant::now();
+    let n_leaves = book.evaluate_leaves(lv, exact_empties, selectivity_lv, true, &mut t_table, &mut evaluator);
+    println!("evaluated leaves: {}, time: {:?}", n_leaves, now.elapsed());
+
+    book.negamax();
+    Ok(book)
+}
+
+/// 定石の拡張の設定
+pub struct BookDeepenConfig {
+    /// 葉の評価に使用する探索レベル
+    pub lv: i32,
+    /// 葉を終局まで読む、空きマス数の最大値
+    pub exact_empties: i32,
+    pub selectivity_lv: i32,
+    /// 拡張する葉の数
+    pub n_expansions: usize,
+    /// 1回のnegamaxの間に拡張する葉の数
+    pub batch_size: usize,
+    /// 定石をファイルに保存する間隔 (拡張した葉の数)
+    pub checkpoint_interval: usize,
+    /// 拡張する葉の、偏差の合計の上限
+    pub max_deviation: i32,
+}
+
+impl Default for BookDeepenConfig {
+    fn default() -> Self {
+        Self {
+            lv: 12,
+            exact_empties: 12,
+            selectivity_lv: 3,
+            n_expansions: 1000,
+            batch_size: 16,
+            checkpoint_interval: 100,
+            max_deviation: i32::MAX,
+        }
+    }
+}
+
+/// 定石を、一時ファイルに書き出してから置き換えることで保存する。(書き込み中に中断しても壊れないように)
+fn save_book(book: &Book, path: &str) -> Result<(), String> {
+    let tmp_path = format!("{}.tmp", path);
+    book.write_file(&tmp_path).map_err(|e| format!("{}: {}", tmp_path, e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn line_to_string(line: &[u8]) -> String {
+    line.iter().map(|&p| Board::move_bit_to_str(1 << p).unwrap()).collect()
+}
+
+/// 定石の葉を探索により拡張し、定石を深くする。
+///
+/// 優先度(`Book::deepen_candidates`)の高い葉から順に、全ての合法手を追加して評価し、negamax法で評価値を初期盤面まで反映する。
+/// `config.checkpoint_interval`個の葉を拡張するごとに、定石を`output`に保存する。
+/// 中断した場合は、保存された定石から再開できる。
+/// 拡張した手順は標準出力に表示し、`report`が指定された場合はそのファイルに追記する。
+///
+/// # 戻り値
+/// * 拡張した葉の数
+pub fn deepen_book(book: &mut Book, config: &BookDeepenConfig, output: &str, report: Option<&str>) -> Result<usize, String> {
+    let mut evaluator = Evaluator::read_file().map_err(|e| e.to_string())?;
+    let mut t_table = TranspositionTable::new();
+    let mut report_file = match report {
+        Some(path) => Some(OpenOptions::new().create(true).append(true).open(path).map_err(|e| format!("{}: {}", path, e))?),
+        None => None
+    };
+
+    book.evaluate_leaves(config.lv, config.exact_empties, config.selectivity_lv, true, &mut t_table, &mut evaluator);
+    book.negamax();
+
+    let now = time::Instant::now();
+    let mut n_expanded = 0;
+    let mut n_since_checkpoint = 0;
+    while n_expanded < config.n_expansions {
+        let batch_size = config.batch_size.max(1).min(config.n_expansions - n_expanded);
+        let candidates = book.deepen_candidates(batch_size, config.max_deviation);
+        if candidates.is_empty() {
+            println!("no more leaves to expand");
+            break;
+        }
+
+        let mut expanded = Vec::with_capacity(candidates.len());
+        for candidate in candidates.iter() {
+            let key = candidate.board.canonical().0;
+            let value = book.nodes[&key].value;
+            let n_added = book.expand(&candidate.board, config.lv, config.exact_empties, config.selectivity_lv, &mut t_table, &mut evaluator);
+            expanded.push((key, value, n_added));
+        }
+        book.negamax();
+
+        for (candidate, (key, value, n_added)) in candidates.iter().zip(expanded) {
+            let line = format!("{} deviation: {}, value: {} -> {}, added: {}",
+                line_to_string(&candidate.line), candidate.deviation, value, book.nodes[&key].value, n_added);
+            println!("{}", line);
+            if let Some(file) = report_file.as_mut() {
+                writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+            }
+        }
+
+        n_expanded += candidates.len();
+        n_since_checkpoint += candidates.len();
+        if n_since_checkpoint >= config.checkpoint_interval {
+            save_book(book, output)?;
+            n_since_checkpoint = 0;
+            println!("checkpoint: {} ({} positions, expanded: {}, time: {:?})", output, book.len(), n_expanded, now.elapsed());
+        }
+    }
+
+    save_book(book, output)?;
+    println!("saved: {} ({} positions, expanded: {}, time: {:?})", output, book.len(), n_expanded, now.elapsed());
+    Ok(n_expanded)
+}
+
+/// 棋譜の文字列 (例: `"f5d6"`) の局面について、定石の着手を表示する。
+pub fn print_book_moves(book: &Book, transcript: &str) -> Result<(), String> {
+    let moves = if transcript.is_empty() {Some(Vec::new())} else {parse_transcript(transcript)};
+    let moves = moves.ok_or(format!("invalid transcript: {}", transcript))?;
+
+    let mut board = Board::new();
+    for &put_place in moves.iter() {
+        board = Book::node_board(&board);
+        board.put_piece(1 << put_place).map_err(|_| format!("invalid transcript: {}", transcript))?;
+    }
+    board.print_board();
+
+    match book.probe(&board) {
+        Some(book_moves) => {
+            for m in book_moves.iter() {
+                println!("{}: {}{}", Board::move_bit_to_str(1 << m.put_place).unwrap(), if m.value > 0 {"+"} else {""}, m.value);
+            }
+        },
+        None => println!("not in book")
+    }
+    Ok(())
+}
+
+/// コマンドラインから、定石の作成・参照を行う。
+///
+/// ```text
+/// deft_reversi_engine book build --output book.bin [--depth 20] [--lv 12] [--exact-empties 12] [--selectivity 3] FILE...
+/// deft_reversi_engine book deepen --book book.bin [--output book.bin] [--lv 12] [--exact-empties 12] [--selectivity 3] [--expansions 1000]
+///                                  [--batch 16] [--checkpoint 100] [--max-deviation 8] [--report deepen.log]
+/// deft_reversi_engine book probe --book book.bin [TRANSCRIPT]
+/// ```
+///
+/// 棋譜ファイルは、拡張子が`.ggf`の場合はGGF形式、`.wtb`の場合はWTHOR形式、それ以外は1行1局の棋譜として読み込む。
+pub fn book_cli(args: &[String]) -> Result<(), String> {
+    let mut output = None;
+    let mut book_path = None;
+    let mut max_depth = 20;
+    let mut lv = 12;
+    let mut exact_empties = 12;
+    let mut selectivity_lv = 3;
+    let mut files = Vec::new();
+    let mut deepen_config = BookDeepenConfig::default();
+    let mut report = None;
+
+    let command = args.first().ok_or("missing book command (build, deepen, probe)")?;
+    let mut args = args[1..].iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--output"      => output = Some(value()?.clone()),
+            "--book"        => book_path = Some(value()?.clone()),
+            "--depth"       => max_depth = value()?.parse().map_err(|_| "invalid depth")?,
+            "--lv"          => lv = value()?.parse().map_err(|_| "invalid lv")?,
+            "--exact-empties" => exact_empties = value()?.parse().map_err(|_| "invalid exact-empties")?,
+            "--selectivity" => selectivity_lv = value()?.parse().map_err(|_| "invalid selectivity")?,
+            "--expansions"  => deepen_config.n_expansions = value()?.parse().map_err(|_| "invalid expansions")?,
+            "--batch"       => deepen_config.batch_size = value()?.parse().map_err(|_| "invalid batch size")?,
+            "--checkpoint"  => deepen_config.checkpoint_interval = value()?.parse().map_err(|_| "invalid checkpoint interval")?,
+            "--max-deviation" => deepen_config.max_deviation = value()?.parse().map_err(|_| "invalid max deviation")?,
+            "--report"      => report = Some(value()?.clone()),
+            _ if arg.starts_with("--") => return Err(format!("unknown option: {}", arg)),
+            _ => files.push(arg.clone())
+        }
+    }
+    if !(0..SELECTIVITY.len() as i32).contains(&selectivity_lv) {
+        return Err(format!("selectivity must be 0 ~ {}", SELECTIVITY.len() - 1));
+    }
+
+    match command.as_str() {
+        "build" => {
+            let output = output.ok_or("missing --output")?;
+            if files.is_empty() {
+                return Err("no game record files".to_string());
+            }
+            let book = build_book(&files, max_depth, lv, exact_empties, selectivity_lv)?;
+            book.write_file(&output).map_err(|e| format!("{}: {}", output, e))?;
+            println!("saved: {} ({} positions)", output, book.len());
+            Ok(())
+        },
+        "deepen" => {
+            let book_path = book_path.ok_or("missing --book")?;
+            let output = output.unwrap_or(book_path.clone());
+            let mut book = Book::read_file(&book_path).map_err(|e| format!("{}: {}", book_path, e))?;
+            deepen_config.lv = lv;
+            deepen_config.exact_empties = exact_empties;
+            deepen_config.selectivity_lv = selectivity_lv;
+            deepen_book(&mut book, &deepen_config, &output, report.as_deref())?;
+            Ok(())
+        },
+        "probe" => {
+            let book_path = book_path.ok_or("missing --book")?;
+            let book = Book::read_file(&book_path).map_err(|e| format!("{}: {}", book_path, e))?;
+            print_book_moves(&book, files.first().map(|s| s.as_str()).unwrap_or(""))
+        },
+        _ => Err(format!("unknown book command: {}", command))
+    }
+}
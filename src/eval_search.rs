@@ -7,12 +7,6 @@ use crate::mpc::*;
 // TranspositionTableでは、評価値をi8で管理している
 const SCORE_INF: i32 = i8::MAX as i32;
 
-const MOVE_ORDERING_EVAL_LEVEL: i32 = 1;
-const MOVE_ORDERING_EVAL_LEVEL_SIMPLE_SEARCH: i32 = 1;
-const SWITCH_SIMPLE_SEARCH_LEVEL: i32 = 6;
-const SWITCH_NEGAALPHA_SEARCH_LEVEL: i32 = 4;
-
-
 /// NegaAlpha法を用いて、オセロの盤面の評価値を計算する。
 ///
 /// 探索速度を向上させるため、葉に近いノードで使用される。
@@ -44,12 +38,12 @@ pub fn negaalpha_eval(board: &Board, mut alpha: i32, beta: i32, lv: i32, search:
     // 合法手がない
     if legal_moves == 0 {
         let mut board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても置くところがない == ゲーム終了
             search.eval_search_node_count += 1;
             search.eval_search_leaf_node_count += 1;
             
-            board.next_turn ^= 1;
+            board.pass();
             return solve_score(&board);
             //return  -implest_eval(&board);
         }
@@ -99,12 +93,12 @@ pub fn negaalpha_eval(board: &Board, mut alpha: i32, beta: i32, lv: i32, search:
 /// # 注記
 /// * 置換表を使用しない。
 /// * 最後の残り数手は、`negaalpha_eval`関数を使用した探索結果を用いる。
-///     * 最後の残り数手は、`SWITCH_NEGAALPHA_SEARCH_LEVEL`で定義される。
+///     * 最後の残り数手は、`SearchConfig::switch_negaalpha_search_level`で定義される。
 pub fn nws_eval_simple(board: &Board, alpha: i32, lv: i32, search: &mut Search) -> i32
 {
     let beta = alpha + 1;
 
-    if lv < SWITCH_NEGAALPHA_SEARCH_LEVEL {
+    if lv < search.config.switch_negaalpha_search_level {
         return negaalpha_eval(board, alpha, beta, lv, search);
     }
 
@@ -113,9 +107,9 @@ pub fn nws_eval_simple(board: &Board, alpha: i32, lv: i32, search: &mut Search)
 
     if legal_moves == 0 {
         let mut board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても置くところがない == ゲーム終了
-            board.next_turn ^= 1;
+            board.pass();
             search.eval_search_node_count += 1;
             search.eval_search_leaf_node_count += 1;
             return solve_score(&board);
@@ -133,12 +127,13 @@ pub fn nws_eval_simple(board: &Board, alpha: i32, lv: i32, search: &mut Search)
     }
 
     // move ordering
-    let put_boards = move_ordering_eval(board, legal_moves, 1, search);
+    let n_empties = board.empties_count();
+    let n_moves = move_ordering_eval(board, legal_moves, 1, search);
 
     let mut this_node_alpha = alpha;
     let mut best_score = -SCORE_INF;
-    for current_put_board in put_boards.iter() {
-        let current_put_board = &current_put_board.board;
+    for i in 0..n_moves {
+        let current_put_board = &search.nth_move(n_empties, i).board;
         let score = -nws_eval_simple(current_put_board, -beta, lv - 1, search);
         if score >= beta {
             return score;
@@ -170,14 +165,14 @@ pub fn nws_eval_simple(board: &Board, alpha: i32, lv: i32, search: &mut Search)
 /// # 注記
 /// * 置換表を使用しない。
 /// * 最後の残り数手は、`negaalpha_eval`関数を使用した探索結果を用いる。
-///     * 最後の残り数手は、`SWITCH_NEGAALPHA_SEARCH_LEVEL`で定義される。
+///     * 最後の残り数手は、`SearchConfig::switch_negaalpha_search_level`で定義される。
 /// 
 pub fn pvs_eval_simple(board: &Board, alpha: i32,beta: i32, lv: i32, search: &mut Search) -> i32
 {   
     #[cfg(debug_assertions)]
     assert!(alpha <= beta);
     
-    if lv < SWITCH_NEGAALPHA_SEARCH_LEVEL {
+    if lv < search.config.switch_negaalpha_search_level {
         return negaalpha_eval(board, alpha, beta, lv, search);
     }
 
@@ -187,9 +182,9 @@ pub fn pvs_eval_simple(board: &Board, alpha: i32,beta: i32, lv: i32, search: &mu
     // pass or end ?
     if legal_moves == 0 { // 合法手がないならば
         let mut board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても合法手がない -> ゲーム終了
-            board.next_turn ^= 1;
+            board.pass();
             search.eval_search_node_count += 1;
             search.eval_search_leaf_node_count += 1;
             return solve_score(&board);
@@ -209,15 +204,14 @@ pub fn pvs_eval_simple(board: &Board, alpha: i32,beta: i32, lv: i32, search: &mu
     }
 
     // move ordering
-    let put_boards =  move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL_SIMPLE_SEARCH, search);
-
-    let mut put_boards_iter = put_boards.iter();
+    let n_empties = board.empties_count();
+    let n_moves =  move_ordering_eval(board, legal_moves, search.config.move_ordering_eval_level_simple_search, search);
     
     let mut this_node_alpha = alpha;
     let mut best_score; //  =  - inf
 
     // first move
-    let first_child_board = put_boards_iter.next().unwrap();
+    let first_child_board = search.nth_move(n_empties, 0);
     best_score =  -pvs_eval_simple(&first_child_board.board, -beta, -this_node_alpha, lv - 1, search);
     if best_score >= beta {
         return best_score;
@@ -225,8 +219,8 @@ pub fn pvs_eval_simple(board: &Board, alpha: i32,beta: i32, lv: i32, search: &mu
     if best_score > this_node_alpha { this_node_alpha = best_score};
 
     // other move
-    for current_put_board in put_boards_iter {
-        let current_put_board = &current_put_board.board;
+    for i in 1..n_moves {
+        let current_put_board = &search.nth_move(n_empties, i).board;
         let mut score = -nws_eval_simple(current_put_board, -this_node_alpha - 1, lv - 1, search);
         if score >= beta {
             return score;
@@ -265,23 +259,28 @@ pub fn pvs_eval_simple(board: &Board, alpha: i32,beta: i32, lv: i32, search: &mu
 /// * 置換表が存在しない場合は、`nvs_perfect_simple` 関数に切り替える。
 /// * `nws_eval_simple` と大きく異なるところは、置換表を使用していることである。
 /// * 最後の残り数手は、`nws_eval_simple`関数を使用した探索結果を用いる。
-///     * 最後の残り数手は、`SWITCH_SIMPLE_SEARCH_LEVEL`で定義される。
+///     * 最後の残り数手は、`SearchConfig::switch_simple_search_level`で定義される。
 pub fn nws_eval(board: &Board, mut alpha: i32, lv: i32, search: &mut Search) -> i32
 {
     let mut beta = alpha + 1;
 
-    if lv < SWITCH_SIMPLE_SEARCH_LEVEL {
+    if lv < search.config.switch_simple_search_level {
         return nws_eval_simple(board, alpha, lv, search);
     }
 
+    // ノード数の上限に達した場合は、探索を打ち切る (結果は使用されない)
+    if search.node_limit_exceeded() {
+        return alpha;
+    }
+
     // 探索範囲: [alpha, beta]
     let legal_moves: u64 = board.put_able();
 
     if legal_moves == 0 {
         let mut board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても置くところがない == ゲーム終了
-            board.next_turn ^= 1;
+            board.pass();
             search.eval_search_node_count += 1;
             search.eval_search_leaf_node_count += 1;
             return solve_score(&board);
@@ -303,16 +302,27 @@ pub fn nws_eval(board: &Board, mut alpha: i32, lv: i32, search: &mut Search) ->
         ProbCutResult::FAIL => ()
     }
 
+    // Enhanced Transposition Cutoff
+    if lv >= search.config.etc_min_level {
+        if let Some((score, put_place)) = etc_cut_off(board, legal_moves, beta, lv - 1, search.selectivity_lv, search.t_table) {
+            search.t_table_add(board, score, SCORE_INF, lv, search.selectivity_lv, put_place);
+            return score;
+        }
+    }
+
     // move ordering
-    let put_boards = move_ordering_eval(board, legal_moves, 1, search);
+    let n_empties = board.empties_count();
+    let n_moves = move_ordering_eval(board, legal_moves, 1, search);
     let mut best_move = NO_COORD;
 
     let mut this_node_alpha = alpha;
     let mut best_score = -SCORE_INF;
-    for put in put_boards.iter() {
+    for i in 0..n_moves {
+        let put = &search.nth_move(n_empties, i);
         let score = -nws_eval(&put.board, -beta, lv - 1, search);
         if score >= beta {
-            search.t_table.add(board, score, SCORE_INF, lv, search.selectivity_lv, put.put_place);
+            search.t_table_add(board, score, SCORE_INF, lv, search.selectivity_lv, put.put_place);
+            search.move_history.update(board, put.put_place, lv);
             return score;
         }
         if score > this_node_alpha {this_node_alpha = score;}
@@ -323,9 +333,9 @@ pub fn nws_eval(board: &Board, mut alpha: i32, lv: i32, search: &mut Search) ->
     }
 
     if best_score > alpha {
-        search.t_table.add(board, best_score, best_score, lv, search.selectivity_lv, best_move);
+        search.t_table_add(board, best_score, best_score, lv, search.selectivity_lv, best_move);
     } else {
-        search.t_table.add(board, -SCORE_INF, best_score, lv, search.selectivity_lv, best_move);
+        search.t_table_add(board, -SCORE_INF, best_score, lv, search.selectivity_lv, best_move);
     }
 
     best_score
@@ -349,9 +359,10 @@ pub fn nws_eval(board: &Board, mut alpha: i32, lv: i32, search: &mut Search) ->
 ///   スコアは現在のプレイヤーから見た盤面の評価値を表す。
 ///
 /// # 例
-/// ```
+/// (`Search`はクレートの外に公開されていないため、doctestとしては実行しない)
+/// ```ignore
 /// let board = Board::new(); // オセロの初期盤面を生成
-/// let mut search = Search::new();
+/// let mut search = Search::new(&board, NO_MPC, &mut t_table, &mut evaluator);
 /// let alpha = -SCORE_INF; // 初期アルファ値の設定
 /// let beta = SCORE_INF; // 初期ベータ値の設定
 /// let lv = 10; // 10手先まで読む
@@ -363,7 +374,7 @@ pub fn nws_eval(board: &Board, mut alpha: i32, lv: i32, search: &mut Search) ->
 /// * 置換表が存在しない場合は、`pvs_perfect_simple` 関数に切り替える。
 /// * `pvs_eval_simple` と大きく異なることは、置換表を使用していることである。
 /// * 最後の残り数手は、`pvs_eval_simple`関数を使用した探索結果を用いる。
-///     * 最後の残り数手は、`SWITCH_SIMPLE_SEARCH_LEVEL`で定義される。
+///     * 最後の残り数手は、`SearchConfig::switch_simple_search_level`で定義される。
 /// 
 pub fn pvs_eval ( board     : &Board,
                   mut alpha : i32,
@@ -372,10 +383,15 @@ pub fn pvs_eval ( board     : &Board,
                   search    : &mut Search)
                   -> i32
 {   
-    if lv < SWITCH_SIMPLE_SEARCH_LEVEL {
+    if lv < search.config.switch_simple_search_level {
         return pvs_eval_simple(board, alpha, beta, lv, search);
     }
 
+    // ノード数の上限に達した場合は、探索を打ち切る (結果は使用されない)
+    if search.node_limit_exceeded() {
+        return alpha;
+    }
+
     #[cfg(debug_assertions)]
     assert!(alpha <= beta);
 
@@ -385,9 +401,9 @@ pub fn pvs_eval ( board     : &Board,
     // pass or end ?
     if legal_moves == 0 { // 合法手がないならば
         let mut board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても合法手がない -> ゲーム終了
-            board.next_turn ^= 1;
+            board.pass();
             search.eval_search_node_count += 1;
             search.eval_search_leaf_node_count += 1;
             return solve_score(&board);
@@ -412,29 +428,31 @@ pub fn pvs_eval ( board     : &Board,
     }
 
     // move ordering
-    let put_boards =  move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL,  search);
-
-    let mut put_boards_iter = put_boards.iter();
+    let n_empties = board.empties_count();
+    let n_moves =  move_ordering_eval(board, legal_moves, search.config.move_ordering_eval_level,  search);
     
     let mut this_node_alpha = alpha;
     let mut best_score; //  =  - inf
 
     // first move
-    let first_child_board = put_boards_iter.next().unwrap();
+    let first_child_board = search.nth_move(n_empties, 0);
     best_score =  -pvs_eval(&first_child_board.board, -beta, -this_node_alpha, lv - 1, search);
     let mut best_move = first_child_board.put_place;
     if best_score >= beta { 
-        search.t_table.add(board, best_score, SCORE_INF, lv, search.selectivity_lv, best_move);
+        search.t_table_add(board, best_score, SCORE_INF, lv, search.selectivity_lv, best_move);
+        search.move_history.update(board, best_move, lv);
         return best_score;
     }
     if best_score > this_node_alpha { this_node_alpha = best_score};
 
     // other move
-    for put in put_boards_iter {
+    for i in 1..n_moves {
+        let put = &search.nth_move(n_empties, i);
         let put_board = &put.board;
         let mut score = -nws_eval( put_board, -this_node_alpha - 1, lv - 1, search);
         if score >= beta {
-            search.t_table.add(board, score, SCORE_INF, lv, search.selectivity_lv, put.put_place);
+            search.t_table_add(board, score, SCORE_INF, lv, search.selectivity_lv, put.put_place);
+            search.move_history.update(board, put.put_place, lv);
             return score;
         }
         if score > best_score {
@@ -443,7 +461,8 @@ pub fn pvs_eval ( board     : &Board,
             if score > this_node_alpha {this_node_alpha = score};
             score = -pvs_eval(put_board, -beta, -this_node_alpha, lv - 1, search);
             if score >= beta { 
-                search.t_table.add(board, score, SCORE_INF, lv, search.selectivity_lv, best_move);
+                search.t_table_add(board, score, SCORE_INF, lv, search.selectivity_lv, best_move);
+                search.move_history.update(board, best_move, lv);
                 return score;
              }
              best_score = score;
@@ -452,9 +471,9 @@ pub fn pvs_eval ( board     : &Board,
     }
 
     if best_score > alpha { // alpha < best_score < beta
-        search.t_table.add(board, best_score, best_score, lv, search.selectivity_lv, best_move);
+        search.t_table_add(board, best_score, best_score, lv, search.selectivity_lv, best_move);
     } else { // best_score <= alpha
-        search.t_table.add(board, -SCORE_INF, best_score, lv, search.selectivity_lv, best_move);
+        search.t_table_add(board, -SCORE_INF, best_score, lv, search.selectivity_lv, best_move);
     }
 
     best_score
@@ -59,7 +59,7 @@ pub fn simplest_eval (board: &Board) -> i32
     
     let player_mobility = board.put_able().count_ones() as i32;
     let mut board = board.clone();
-    board.next_turn ^= 1;
+    board.pass();
     let opponent_mobility = board.put_able().count_ones() as i32;
 
     let mobility_score = player_mobility - opponent_mobility;
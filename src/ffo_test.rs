@@ -1,63 +1,342 @@
 use std::time;
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
 
 use crate::{board::*, t_table::*};
 use crate::solver::*;
 use crate::eval::*;
+use crate::mpc::SELECTIVITY;
+use crate::search_config::*;
+use crate::search_observer::NullObserver;
 
-pub fn ffo_test() -> Result<(),  std::io::Error> {
+/// FFO endgame test の実行設定
+pub struct FfoTestConfig {
+    /// 実行する問題番号 (`data/ffo_test/end{No.}.pos`)
+    pub positions: Vec<i32>,
+    /// 並列に実行するスレッド数 (各スレッドは、置換表と評価関数をそれぞれ持つ)
+    pub n_threads: usize,
+    pub selectivity_lv: i32,
+    /// 探索のパラメータ
+    pub search_config: SearchConfig,
+}
 
-    let mut evaluator = Evaluator::read_file().unwrap();
-    let mut t = TranspositionTable::new();
-    for i in 40..60 {
-        let filename = format!("data/ffo_test/end{}.pos", i);
-        let board = match read_ffo_test_files(&filename) {
-            Ok(it) => it,
-            Err(err) => {
-                eprintln!("Error reading the file {}: {}", filename, err);
-                continue;
-            },
-        };
+impl Default for FfoTestConfig {
+    fn default() -> Self {
+        Self {
+            positions: (40..60).collect(),
+            n_threads: 1,
+            selectivity_lv: 3,
+            search_config: SearchConfig::default(),
+        }
+    }
+}
 
-        let selectivity_lv = 3;
-    
-        println!("#{} ", i);
-        // board.print_board();
-        println!("    num of empties: {}", board.empties_count());
-        println!("    selectivity   : {} %", crate::mpc::SELECTIVITY[selectivity_lv as usize].percent);
-        
-        let now = time::Instant::now();
-        let solver_result = 
-            // match eval_solver(&board, 20, selectivity_lv, false, &mut t, &mut evaluator) {
-
-            match perfect_solver(&board, false, selectivity_lv, &mut t, &mut evaluator) {
-                Ok(result) => result,
-                Err(e) => {
-                        eprintln!("Error occurred in perfect solver.");
-                        panic!();
-                    }
+/// 1局面分の実行結果
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FfoTestRecord {
+    pub no: i32,
+    pub n_empties: i32,
+    pub selectivity_lv: i32,
+    pub score: i32,
+    pub best_move: String,
+    pub time: f64,
+    pub node_count: u64,
+    pub nps: f64,
+}
+
+/// FFO endgame test の実行結果
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct FfoTestReport {
+    pub records: Vec<FfoTestRecord>,
+    pub total_time: f64,
+    pub total_node_count: u64,
+}
+
+impl FfoTestRecord {
+    /// 既知の正しい結果(`FFO_TEST_RESULTS`)と比較する。
+    ///
+    /// # 戻り値
+    /// * 既知の結果がない場合は、`None`
+    pub fn is_correct(&self) -> Option<bool> {
+        FFO_TEST_RESULTS.iter()
+            .find(|r| r.0 == self.no)
+            .map(|(_, score, best_moves)| self.score == *score && best_moves.contains(&self.best_move.as_str()))
+    }
+}
+
+impl FfoTestReport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("no,n_empties,selectivity_lv,score,best_move,time,node_count,nps\n");
+        for r in self.records.iter() {
+            csv.push_str(&format!("{},{},{},{},{},{},{},{}\n",
+                r.no, r.n_empties, r.selectivity_lv, r.score, r.best_move, r.time, r.node_count, r.nps));
+        }
+        csv
+    }
+
+    /// 実行結果をファイルに書き出す。拡張子が`.csv`の場合はCSV、それ以外はJSONで出力する。
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let is_csv = path.as_ref().extension().is_some_and(|e| e == "csv");
+        fs::write(path, if is_csv {self.to_csv()} else {self.to_json()})
+    }
+
+    /// JSONで書き出した実行結果を読み込む。
+    pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<FfoTestReport> {
+        let input = fs::read_to_string(path)?;
+        serde_json::from_str(&input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn print(&self) {
+        for r in self.records.iter() {
+            println!("#{} ", r.no);
+            println!("    num of empties: {}", r.n_empties);
+            println!("    selectivity   : {} %", SELECTIVITY[r.selectivity_lv as usize].percent);
+            println!("    score         : {:+}", r.score);
+            println!("    best move     : {  }", r.best_move);
+            println!("    time          : {:?}", time::Duration::from_secs_f64(r.time));
+            println!("    node          : {  }", r.node_count);
+            println!("    nps [/s]      : {  }", r.nps);
+            if let Some(ok) = r.is_correct() {
+                println!("    result        : {}", if ok {"OK"} else {"NG"});
+            }
+            println!();
+        }
+        println!("total time: {:?}", time::Duration::from_secs_f64(self.total_time));
+        println!("total node: {}", self.total_node_count);
+        println!("nps [/s]  : {}", self.total_node_count as f64 / self.total_time);
+    }
+
+    /// 以前の実行結果と比較し、スコアの変化と、速度の低下を検出する。
+    ///
+    /// # 引数
+    /// * `previous` - 比較対象の、以前の実行結果
+    /// * `speed_tolerance` - 許容する実行時間の増加率 (例: 0.1 のとき、10%以上遅くなった場合に速度の低下とする)
+    ///
+    /// # 戻り値
+    /// * `(スコアの変化の数, 速度の低下の数)`
+    pub fn compare(&self, previous: &FfoTestReport, speed_tolerance: f64) -> (usize, usize) {
+        // 実行時間が短すぎる局面は、誤差が大きいため速度の比較を行わない
+        const MIN_TIME_FOR_SPEED_COMPARISON: f64 = 0.1;
+
+        let mut n_score_changes = 0;
+        let mut n_speed_regressions = 0;
+
+        for r in self.records.iter() {
+            let Some(p) = previous.records.iter().find(|p| p.no == r.no) else {
+                println!("#{}: not found in previous report", r.no);
+                continue;
             };
-            
-        let end = now.elapsed();
-        println!("    score         : {:+}", solver_result.eval);
-        println!("    best move     : {  }", Board::move_bit_to_str(solver_result.best_move).unwrap());
-        println!("    time          : {:?}", end);
-        println!("    node          : {  }", solver_result.node_count);
-        println!("    nps [/s]      : {  }", solver_result.node_count as f64 / end.as_secs_f64());
-        println!();
 
+            let mut notes = Vec::new();
+            if r.selectivity_lv != p.selectivity_lv {
+                notes.push(format!("selectivity changed ({} -> {})", p.selectivity_lv, r.selectivity_lv));
+            }
+            if r.score != p.score {
+                n_score_changes += 1;
+                notes.push(format!("SCORE CHANGED ({:+} -> {:+})", p.score, r.score));
+            } else if r.best_move != p.best_move {
+                notes.push(format!("best move changed ({} -> {})", p.best_move, r.best_move));
+            }
+            let time_ratio = r.time / p.time;
+            if r.time.max(p.time) >= MIN_TIME_FOR_SPEED_COMPARISON && time_ratio > 1.0 + speed_tolerance {
+                n_speed_regressions += 1;
+                notes.push("SLOWER".to_string());
+            }
+
+            println!("#{}: time {:>8.3}s -> {:>8.3}s ({:+6.1} %), node {:>12} -> {:>12} ({:+6.1} %) {}",
+                r.no, p.time, r.time, (time_ratio - 1.0) * 100.0,
+                p.node_count, r.node_count, (r.node_count as f64 / p.node_count as f64 - 1.0) * 100.0,
+                notes.join(", "));
+        }
+
+        for p in previous.records.iter().filter(|p| !self.records.iter().any(|r| r.no == p.no)) {
+            println!("#{}: not found in this report", p.no);
+        }
+
+        println!("total time: {:.3}s -> {:.3}s ({:+.1} %)",
+            previous.total_time, self.total_time, (self.total_time / previous.total_time - 1.0) * 100.0);
+        println!("score changes: {}, speed regressions: {}", n_score_changes, n_speed_regressions);
+
+        (n_score_changes, n_speed_regressions)
+    }
+}
+
+/// 問題番号の集合を表す文字列を解釈する。 (例: `"40-59"`, `"40,41,50-59"`)
+pub fn parse_positions(s: &str) -> Result<Vec<i32>, String> {
+    let mut positions = Vec::new();
+    for part in s.split(',') {
+        let parse = |x: &str| x.trim().parse::<i32>().map_err(|_| format!("invalid position: {}", x));
+        match part.split_once('-') {
+            Some((first, last)) => positions.extend(parse(first)?..=parse(last)?),
+            None => positions.push(parse(part)?)
+        }
+    }
+    Ok(positions)
+}
+
+/// FFO endgame test を、`perfect_solver`で実行する。
+///
+/// 問題は、`config.n_threads`個のスレッドで並列に解かれる。
+///
+/// # 戻り値
+/// * 問題ファイルが存在しない場合や、`perfect_solver`が失敗した問題がある場合は、エラー
+///   (レポートから問題が欠けると、比較で気付けないため)
+pub fn run_ffo_test(config: &FfoTestConfig) -> io::Result<FfoTestReport> {
+    let mut boards = Vec::new();
+    let mut missing = Vec::new();
+    for &i in config.positions.iter() {
+        match read_ffo_position(i) {
+            Ok(board) => boards.push((i, board)),
+            Err(_) => missing.push(ffo_position_path(i)),
+        }
+    }
+    if !missing.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound,
+            format!("position files not found: {} (see http://radagast.se/othello/ffotest.html)", missing.join(", "))));
     }
 
+    let n_threads = config.n_threads.clamp(1, boards.len().max(1));
+    let mut evaluators = Vec::with_capacity(n_threads);
+    for _ in 0..n_threads {
+        evaluators.push(Evaluator::read_file()?);
+    }
+
+    let next_index = AtomicUsize::new(0);
+    let records = Mutex::new(Vec::new());
+    let failed = Mutex::new(Vec::new());
+    let now = time::Instant::now();
+
+    thread::scope(|s| {
+        let (boards, next_index, records, failed) = (&boards, &next_index, &records, &failed);
+        for mut evaluator in evaluators {
+            s.spawn(move || {
+                let mut t = TranspositionTable::new();
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    let Some((i, board)) = boards.get(index) else {break};
+
+                    let now = time::Instant::now();
+                    let Ok(solver_result) = perfect_solver_with_observer(board, config.selectivity_lv, &mut t, &mut evaluator, &config.search_config, &mut NullObserver) else {
+                        failed.lock().unwrap().push(*i);
+                        continue;
+                    };
+                    let end = now.elapsed().as_secs_f64();
+
+                    records.lock().unwrap().push(FfoTestRecord {
+                        no: *i,
+                        n_empties: board.empties_count(),
+                        selectivity_lv: config.selectivity_lv,
+                        score: solver_result.eval,
+                        best_move: Board::move_bit_to_str(solver_result.best_move).unwrap(),
+                        time: end,
+                        node_count: solver_result.node_count,
+                        nps: solver_result.node_count as f64 / end,
+                    });
+                }
+            });
+        }
+    });
+
+    let mut failed = failed.into_inner().unwrap();
+    if !failed.is_empty() {
+        failed.sort();
+        let failed: Vec<String> = failed.iter().map(|i| format!("#{}", i)).collect();
+        return Err(io::Error::other(format!("perfect_solver failed: {}", failed.join(", "))));
+    }
+
+    let mut records = records.into_inner().unwrap();
+    records.sort_by_key(|r| r.no);
+    let total_node_count = records.iter().map(|r| r.node_count).sum();
+
+    Ok(FfoTestReport {
+        records,
+        total_time: now.elapsed().as_secs_f64(),
+        total_node_count,
+    })
+}
+
+pub fn ffo_test() -> Result<(),  std::io::Error> {
+    let report = run_ffo_test(&FfoTestConfig::default())?;
+    report.print();
     Ok(())
 }
 
+/// コマンドラインから、FFO endgame test を実行する。
+///
+/// ```text
+/// deft_reversi_engine ffo [--positions 40-59] [--threads 1] [--selectivity 3] [--config config.json]
+///                         [--output report.json|report.csv] [--compare previous.json] [--tolerance 0.1]
+/// ```
+///
+/// # 戻り値
+/// * 比較対象がある場合、スコアの変化があれば`false`
+pub fn ffo_test_cli(args: &[String]) -> Result<bool, String> {
+    let mut config = FfoTestConfig::default();
+    let mut output = None;
+    let mut compare = None;
+    let mut speed_tolerance = 0.1;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--positions"   => config.positions = parse_positions(value()?)?,
+            "--threads"     => config.n_threads = value()?.parse().map_err(|_| "invalid thread count")?,
+            "--selectivity" => config.selectivity_lv = value()?.parse().map_err(|_| "invalid selectivity")?,
+            "--config"      => {
+                let path = value()?;
+                config.search_config = SearchConfig::read_file(path).map_err(|e| format!("{}: {}", path, e))?;
+            },
+            "--output"      => output = Some(value()?.clone()),
+            "--compare"     => compare = Some(value()?.clone()),
+            "--tolerance"   => speed_tolerance = value()?.parse().map_err(|_| "invalid tolerance")?,
+            _ => return Err(format!("unknown option: {}", arg))
+        }
+    }
+    if !(0..SELECTIVITY.len() as i32).contains(&config.selectivity_lv) {
+        return Err(format!("selectivity must be 0 ~ {}", SELECTIVITY.len() - 1));
+    }
+    let report = run_ffo_test(&config).map_err(|e| e.to_string())?;
+    report.print();
+
+    if let Some(path) = output {
+        report.write_file(&path).map_err(|e| format!("{}: {}", path, e))?;
+    }
+
+    if let Some(path) = compare {
+        let previous = FfoTestReport::read_file(&path).map_err(|e| format!("{}: {}", path, e))?;
+        println!();
+        let (n_score_changes, _) = report.compare(&previous, speed_tolerance);
+        return Ok(n_score_changes == 0);
+    }
+
+    Ok(true)
+}
+
+fn ffo_position_path(no: i32) -> String {
+    format!("data/ffo_test/end{}.pos", no)
+}
+
+/// FFO endgame test の問題`no`の局面を読み込む。(`data/ffo_test/end{no}.pos`)
+pub fn read_ffo_position(no: i32) -> io::Result<Board> {
+    read_ffo_test_files(ffo_position_path(no))
+}
+
 fn read_ffo_test_files<P: AsRef<Path>>(filename: P) -> io::Result<Board> {
     let file = File::open(filename)?;
     let reader = io::BufReader::new(file);
-    let mut board = Board { bit_board: [0;2], next_turn: Board::BLACK };
+    let mut bit_board = [0u64; 2];
 
     let mut lines = reader.lines();
 
@@ -65,10 +344,10 @@ fn read_ffo_test_files<P: AsRef<Path>>(filename: P) -> io::Result<Board> {
     for (i,c) in first_line.chars().enumerate() {
         match c {
             'O' => {
-                board.bit_board[Board::WHITE] |= 1 << i;
+                bit_board[Board::WHITE] |= 1 << i;
             },
             'X' => {
-                board.bit_board[Board::BLACK] |= 1 << i;
+                bit_board[Board::BLACK] |= 1 << i;
             }
             _ => ()
         }
@@ -77,11 +356,65 @@ fn read_ffo_test_files<P: AsRef<Path>>(filename: P) -> io::Result<Board> {
     let second_line = lines.next().unwrap().unwrap();
     // println!("{}",first_line);
     // println!("{}",second_line);
-    if second_line.contains("Black") {
-        board.next_turn = Board::BLACK;
+    let next_turn = if second_line.contains("Black") {
+        Board::BLACK
     }else {
-        board.next_turn = Board::WHITE;
-    }
+        Board::WHITE
+    };
+
+    Ok(Board::from_bit_board(bit_board, next_turn))
+}
+
+/// FFO endgame test (#40 ~ #59) の、正しい最終スコアと最善手
+///
+/// http://radagast.se/othello/ffotest.html
+pub const FFO_TEST_RESULTS: [(i32, i32, &[&str]); 20] = [
+    // (No., score, best moves)
+    (40, 38, &["a2"]),
+    (41, 0, &["h4"]),
+    (42, 6, &["g2"]),
+    (43, -12, &["c7", "g3"]),
+    (44, -14, &["d2", "b8"]),
+    (45, 6, &["b2"]),
+    (46, -8, &["b3"]),
+    (47, 4, &["g2"]),
+    (48, 28, &["f6"]),
+    (49, 16, &["e1"]),
+    (50, 10, &["d8"]),
+    (51, 6, &["e2", "a3"]),
+    (52, 0, &["a3"]),
+    (53, -2, &["d8"]),
+    (54, -2, &["c7"]),
+    (55, 0, &["g6", "b7", "e2", "g4"]),
+    (56, 2, &["h5"]),
+    (57, -10, &["a6"]),
+    (58, 4, &["g1"]),
+    (59, 64, &["g8", "e8"]),
+];
 
-    Ok(board)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpc::NO_MPC;
+
+    /// FFO endgame test の全ての局面を、MPCを使用せずに完全読みし、正しい結果が得られることを確認する。
+    ///
+    /// 時間がかかるため、通常は実行しない。
+    /// `cargo test --release ffo_perfect_solver -- --ignored` で実行する。
+    #[test]
+    #[ignore]
+    fn ffo_perfect_solver() {
+        let mut evaluator = Evaluator::read_file().unwrap_or_default();
+        let mut t = TranspositionTable::new();
+
+        for (i, score, best_moves) in FFO_TEST_RESULTS {
+            let board = read_ffo_test_files(format!("data/ffo_test/end{}.pos", i)).unwrap();
+            let Ok(result) = perfect_solver(&board, false, NO_MPC, &mut t, &mut evaluator) else {
+                panic!("#{}: perfect_solver returned an error", i);
+            };
+            let best_move = Board::move_bit_to_str(result.best_move).unwrap();
+            assert_eq!(result.eval, score, "#{}", i);
+            assert!(best_moves.contains(&best_move.as_str()), "#{}: best move: {}", i, best_move);
+        }
+    }
 }
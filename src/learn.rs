@@ -89,7 +89,7 @@ fn gen_training_data(filename: &str) -> Vec<Training> {
         if record[i] == '\n'{
             let mut board = training_data.last().unwrap().bm.current_board();
             let td = training_data.last_mut().unwrap();
-            board.next_turn = Board::BLACK;
+            if board.next_turn != Board::BLACK {board.pass();}
             td.score_black = solve_score(&board);
             // println!("score: {}", td.score_black);
 
@@ -105,7 +105,7 @@ fn gen_training_data(filename: &str) -> Vec<Training> {
 
         // println!("move: {}", Board::move_bit_to_str(move_bit).unwrap());
         if board.put_piece(move_bit).is_err() {
-            board.next_turn ^= 1;
+            board.pass();
             if board.put_piece(move_bit).is_err() {
                 panic!();
             }
@@ -263,7 +263,7 @@ pub fn npc_perfect_learn() {
         training_data.append(&mut gen_training_data(&filename));
     }
 
-    use crate::mpc::PERFECT_SEARCH_MPC_SEARCH_PARAMS;
+    use crate::mpc::perfect_search_mpc_params;
     use crate::mpc::MpcParams;
 
     println!("n_empties, Search depth for prob cut, Search score, Search score for prob cut ");
@@ -276,7 +276,7 @@ pub fn npc_perfect_learn() {
         for board in training_datum.bm.board_record.iter(){
             let n_empties = board.empties_count();
             let mpc_search_lv = 
-                match &PERFECT_SEARCH_MPC_SEARCH_PARAMS[n_empties as usize] {
+                match &perfect_search_mpc_params(n_empties) {
                     Some(mpc_params) => {
                         mpc_params.lv
                     },
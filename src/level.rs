@@ -0,0 +1,195 @@
+use crate::board::*;
+use crate::book::Book;
+use crate::solver::*;
+use crate::t_table::*;
+use crate::eval::*;
+pub use crate::search_observer::SearchType;
+
+/// 強さのレベルに対応する探索の設定
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Level {
+    /// 中盤の探索の深さ (`eval_solver`の`lv`)
+    pub mid_lv: i32,
+    /// 石差の完全読みを行う空きマス数 (この値以下で`perfect_solver`を使用する)
+    pub exact_empties: i32,
+    /// 勝敗の完全読みを行う空きマス数 (この値以下で`winning_solver`を使用する)
+    pub wld_empties: i32,
+    /// `eval_solver`と`perfect_solver`の`selectivity_lv`
+    ///
+    /// `winning_solver`は、常にMPCを使用しない。
+    pub selectivity_lv: i32,
+}
+
+pub const MIN_LEVEL: i32 = 1;
+pub const MAX_LEVEL: i32 = 30;
+
+const fn level(mid_lv: i32, exact_empties: i32, wld_empties: i32, selectivity_lv: i32) -> Level {
+    Level { mid_lv, exact_empties, wld_empties, selectivity_lv }
+}
+
+/// レベル 1 ~ 30 の設定 (`LEVELS[level - 1]`)
+pub const LEVELS: [Level; MAX_LEVEL as usize] = [
+    //    mid, exact, wld, selectivity
+    level( 1,  2,  2, 0), // 1
+    level( 2,  4,  4, 0),
+    level( 3,  6,  6, 0),
+    level( 4,  8,  8, 0),
+    level( 5, 10, 10, 0), // 5
+    level( 6, 12, 12, 0),
+    level( 7, 14, 14, 3),
+    level( 8, 16, 16, 3),
+    level( 9, 18, 18, 3),
+    level(10, 20, 20, 3), // 10
+    level(11, 20, 21, 3),
+    level(12, 21, 22, 3),
+    level(13, 21, 23, 3),
+    level(14, 22, 24, 3),
+    level(15, 22, 24, 2), // 15
+    level(16, 23, 25, 2),
+    level(17, 24, 26, 2),
+    level(18, 24, 27, 2),
+    level(19, 25, 28, 2),
+    level(20, 26, 28, 1), // 20
+    level(21, 26, 29, 1),
+    level(22, 27, 30, 1),
+    level(23, 28, 30, 1),
+    level(24, 28, 31, 1),
+    level(25, 29, 32, 1), // 25
+    level(26, 30, 32, 1),
+    level(27, 30, 33, 1),
+    level(28, 31, 34, 1),
+    level(29, 32, 34, 1),
+    level(30, 32, 36, 1), // 30
+];
+
+impl Level {
+    /// レベルの設定を取得する。範囲外のレベルは、1 ~ 30 に丸められる。
+    pub fn get(level: i32) -> Level {
+        LEVELS[(level.clamp(MIN_LEVEL, MAX_LEVEL) - MIN_LEVEL) as usize]
+    }
+
+    /// 空きマス数に対して使用する探索の種類
+    pub fn search_type(&self, n_empties: i32) -> SearchType {
+        if n_empties <= self.exact_empties {
+            SearchType::Exact
+        } else if n_empties <= self.wld_empties {
+            SearchType::Wld
+        } else {
+            SearchType::Midgame
+        }
+    }
+}
+
+/// `best_move`の結果
+pub struct LevelSearchResult {
+    /// 探索結果
+    ///
+    /// `search_type`が`SearchType::Wld`の場合、`eval`は勝ち:1、引き分け:0、負け:-1。
+    pub solver_result: SolverResult,
+    /// 使用した探索の種類
+    pub search_type: SearchType,
+}
+
+/// レベルの設定に従って探索を行い、最善手を求める。
+///
+/// 空きマス数に応じて、`eval_solver`、`winning_solver`、`perfect_solver`を使い分ける。
+/// 中盤で`book`に盤面がある場合は、探索せずに定石の最善手を返す。(`eval_solver_with_book`)
+///
+/// # 引数
+/// * `board` - 探索する盤面
+/// * `level` - 強さのレベル (1 ~ 30)
+/// * `book` - 参照する定石 (`None`の場合は、常に探索する)
+///
+/// # 戻り値
+/// * 合法手が存在しない場合は、`SolverErr::NoMove`
+pub fn best_move(board: &Board, level: i32, book: Option<&Book>, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> Result<LevelSearchResult, SolverErr> {
+    let level = Level::get(level);
+    let search_type = level.search_type(board.empties_count());
+    let solver_result = match (search_type, book) {
+        (SearchType::Exact, _)            => perfect_solver(board, false, level.selectivity_lv, t_table, evaluator)?,
+        (SearchType::Wld, _)              => winning_solver(board, false, t_table, evaluator)?,
+        (SearchType::Midgame, Some(book)) => eval_solver_with_book(board, book, level.mid_lv, level.selectivity_lv, false, t_table, evaluator)?,
+        (SearchType::Midgame, None)       => eval_solver(board, level.mid_lv, level.selectivity_lv, false, t_table, evaluator)?,
+    };
+    Ok(LevelSearchResult { solver_result, search_type })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::parse_transcript;
+
+    #[test]
+    fn levels_are_monotonic() {
+        for (i, w) in LEVELS.windows(2).enumerate() {
+            assert!(w[0].mid_lv < w[1].mid_lv, "level: {}", i + 2);
+            assert!(w[0].exact_empties <= w[1].exact_empties, "level: {}", i + 2);
+            assert!(w[0].wld_empties <= w[1].wld_empties, "level: {}", i + 2);
+        }
+        for level in LEVELS.iter() {
+            assert!(level.exact_empties <= level.wld_empties);
+        }
+        assert_eq!(Level::get(0), LEVELS[0]);
+        assert_eq!(Level::get(100), LEVELS[MAX_LEVEL as usize - 1]);
+    }
+
+    #[test]
+    fn search_type_by_empties() {
+        let level = Level::get(12);
+        assert_eq!(level.search_type(21), SearchType::Exact);
+        assert_eq!(level.search_type(22), SearchType::Wld);
+        assert_eq!(level.search_type(23), SearchType::Midgame);
+    }
+
+    #[test]
+    fn best_move_uses_exact_search_in_endgame() {
+        // 空きマスが8になるまで、手番側の最初の合法手を打つ
+        let mut board = Board::new();
+        while board.empties_count() > 8 {
+            if board.put_able() == 0 {
+                board.pass();
+                continue;
+            }
+            let legal_moves = board.put_able();
+            board.put_piece_fast(legal_moves & legal_moves.wrapping_neg());
+        }
+        if board.put_able() == 0 {
+            board.pass();
+        }
+
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+        let Ok(result) = best_move(&board, 5, None, &mut t_table, &mut evaluator) else {panic!()};
+        assert_eq!(result.search_type, SearchType::Exact);
+        let Ok(expected) = perfect_solver(&board, false, 0, &mut TranspositionTable::new(), &mut evaluator) else {panic!()};
+        assert_eq!(result.solver_result.eval, expected.eval);
+
+        let Ok(result) = best_move(&Board::new(), 5, None, &mut t_table, &mut evaluator) else {panic!()};
+        assert_eq!(result.search_type, SearchType::Midgame);
+        assert_ne!(Board::new().put_able() & result.solver_result.best_move, 0);
+    }
+
+    #[test]
+    fn best_move_plays_book_moves_without_searching() {
+        let mut book = Book::new();
+        assert!(book.add_line(&parse_transcript("f5d6").unwrap(), 60));
+        assert!(book.add_line(&parse_transcript("f5f6").unwrap(), 60));
+        let mut board = Board::new();
+        board.put_piece_fast(1 << F5);
+        let mut f5d6 = board.clone();
+        f5d6.put_piece_fast(1 << D6);
+        book.nodes.get_mut(&f5d6.canonical().0).unwrap().value = -4;
+        book.negamax();
+
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+        let Ok(result) = best_move(&board, 5, Some(&book), &mut t_table, &mut evaluator) else {panic!()};
+        assert_eq!(result.solver_result.best_move, 1 << D6);
+        assert_eq!(result.solver_result.eval, 4);
+        assert_eq!(result.solver_result.node_count, 0);
+
+        // 定石にない盤面は探索する
+        let Ok(result) = best_move(&f5d6, 5, Some(&book), &mut t_table, &mut evaluator) else {panic!()};
+        assert!(result.solver_result.node_count > 0);
+    }
+}
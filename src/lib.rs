@@ -4,8 +4,23 @@ pub mod perfect_search;
 pub mod eval_search;
 pub mod solver;
 pub mod board_manager;
+pub mod perft;
+pub mod book;
+pub mod play_style;
+pub mod level;
+pub mod search_observer;
+pub mod search_config;
+pub mod ffo_test;
+pub mod book_tool;
+pub mod search_tool;
+pub mod tune_tool;
+pub mod mpc_tool;
+pub mod learn;
+mod eval_for_learn;
 mod bit;
+mod zobrist;
 mod search;
+mod move_history;
 mod t_table;
 mod eval;
 mod mpc;
@@ -17,6 +32,7 @@ pub use solver::*;
 pub use board_manager::*;
 pub use eval::*;
 pub use t_table::*;
+pub use search_config::*;
 
 
 
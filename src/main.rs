@@ -1,32 +1,73 @@
-mod eval_simple;
-mod board;
-mod bit;
-mod t_table;
-mod search;
-mod perfect_search;
-mod eval_search;
-mod solver;
-mod board_manager;
-mod eval;
-mod learn;
-mod ffo_test;
-mod eval_for_learn;
-mod mpc;
-
 // mod game;
 // ---
 
 
-use ffo_test::*;
-use learn::*;
-use eval::*;
+use deft_reversi_engine::ffo_test::*;
+use deft_reversi_engine::learn::*;
+use deft_reversi_engine::perft::*;
+use deft_reversi_engine::book_tool::*;
+use deft_reversi_engine::search_tool::*;
+use deft_reversi_engine::tune_tool::*;
+use deft_reversi_engine::mpc_tool::*;
 
 
 fn main () {
 
-    // npc_perfect_learn();
-    // npc_learn(10);
-    // learning();
-    ffo_test();
+    let args: Vec<String> = std::env::args().collect();
+
+    match args.get(1).map(|s| s.as_str()) {
+        Some("perft") => {
+            // 例: deft_reversi_engine perft 11
+            let depth = args.get(2).and_then(|s| s.parse().ok()).unwrap_or(11);
+            if !perft_test(depth) {
+                std::process::exit(1);
+            }
+        },
+        Some("ffo") => {
+            // 例: deft_reversi_engine ffo --positions 40-59 --threads 4 --selectivity 0 --output report.json
+            match ffo_test_cli(&args[2..]) {
+                Ok(true) => (),
+                Ok(false) => std::process::exit(1),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(2);
+                }
+            }
+        },
+        Some("book") => {
+            // 例: deft_reversi_engine book build --output book.bin --depth 20 --lv 12 games.ggf
+            if let Err(e) = book_cli(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        Some("search") => {
+            // 例: deft_reversi_engine search --moves f5d6c3 --lv 60 --nodes 1000000
+            if let Err(e) = search_cli(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        Some("tune") => {
+            // 例: deft_reversi_engine tune --random 20 --empties 18 --param etc_min_empties=8..16 --output best.json
+            if let Err(e) = tune_cli(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        Some("mpc") => {
+            // 例: deft_reversi_engine mpc --games games.ggf --per-empties 200 --samples samples.csv --output mpc.json
+            if let Err(e) = mpc_cli(&args[2..]) {
+                eprintln!("{}", e);
+                std::process::exit(2);
+            }
+        },
+        _ => {
+            // npc_perfect_learn();
+            // npc_learn(10);
+            // learning();
+            ffo_test();
+        }
+    }
 
 }
\ No newline at end of file
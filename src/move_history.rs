@@ -0,0 +1,130 @@
+use crate::board::*;
+
+/// キラー手に与えるmove orderingの加点 (`[1番目のキラー手, 2番目のキラー手]`)
+const KILLER_BONUS: [i32; 2] = [6, 3];
+
+/// ヒストリーに基づく加点の最大値
+const HISTORY_BONUS_MAX: i32 = 4;
+
+/// ヒストリーの値がこれを超えた場合は、全ての値を半分にする
+const HISTORY_MAX: u32 = 1 << 30;
+
+/// move orderingでは、評価値を`MOVE_HISTORY_SCALE`倍した値に加点する。
+///
+/// 加点の合計は`MOVE_HISTORY_SCALE`未満であり、評価値が同じ手の間の順序のみを決める。
+pub const MOVE_HISTORY_SCALE: i32 = 16;
+
+/// 手数ごとに保存するキラー手の数
+const N_KILLERS: usize = 2;
+
+/// キラー手とヒストリーの表
+///
+/// `pvs_eval`, `nws_eval`, `pvs_perfect`, `nws_perfect`でbeta cutを起こした手を記録し、`move_ordering_eval`で用いる。
+/// * キラー手 : 同じ手数の局面でbeta cutを起こした手 (手数ごとに直近の2手)
+/// * ヒストリー : 手番とマスごとの、beta cutを起こした回数を探索の深さで重み付けした値
+pub struct MoveHistory {
+    /// `false`の場合は、記録も加点も行わない
+    pub enabled: bool,
+    killers: [[u8; N_KILLERS]; 61],
+    history: [[u32; 64]; 2],
+}
+
+impl MoveHistory {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            killers: [[NO_COORD; N_KILLERS]; 61],
+            history: [[0; 64]; 2],
+        }
+    }
+
+    /// `board`で、`put_place`が深さ`lv`の探索でbeta cutを起こしたことを記録する。
+    #[inline(always)]
+    pub fn update(&mut self, board: &Board, put_place: u8, lv: i32) {
+        if !self.enabled {
+            return;
+        }
+
+        let killers = &mut self.killers[board.move_count() as usize];
+        if killers[0] != put_place {
+            killers[1] = killers[0];
+            killers[0] = put_place;
+        }
+
+        let history = &mut self.history[board.next_turn];
+        history[put_place as usize] += (lv * lv) as u32;
+        if history[put_place as usize] > HISTORY_MAX {
+            for h in self.history.iter_mut().flatten() {
+                *h /= 2;
+            }
+        }
+    }
+
+    /// `board`の合法手`legal_moves`について、move orderingの加点を求める。
+    ///
+    /// ヒストリーによる加点は、合法手の中で最もヒストリーの値が大きい手を`HISTORY_BONUS_MAX`とした相対的な値である。
+    ///
+    /// # 戻り値
+    /// * マスごとの加点 (無効な場合は、全て`0`)
+    #[inline(always)]
+    pub fn bonus(&self, board: &Board, legal_moves: u64) -> [i32; 64] {
+        let mut bonus = [0; 64];
+        if !self.enabled {
+            return bonus;
+        }
+
+        for (&killer, killer_bonus) in self.killers[board.move_count() as usize].iter().zip(KILLER_BONUS) {
+            if killer != NO_COORD {
+                bonus[killer as usize] += killer_bonus;
+            }
+        }
+
+        let history = &self.history[board.next_turn];
+        let mut moves = legal_moves;
+        let mut max_history = 0;
+        while moves != 0 {
+            max_history = max_history.max(history[moves.trailing_zeros() as usize]);
+            moves &= moves - 1;
+        }
+        if max_history > 0 {
+            let mut moves = legal_moves;
+            while moves != 0 {
+                let put_place = moves.trailing_zeros() as usize;
+                moves &= moves - 1;
+                bonus[put_place] += (HISTORY_BONUS_MAX as u64 * history[put_place] as u64 / max_history as u64) as i32;
+            }
+        }
+        bonus
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn killers_and_history_order_moves() {
+        let board = Board::new();
+        let legal_moves = board.put_able();
+        let mut move_history = MoveHistory::new(true);
+        assert_eq!(move_history.bonus(&board, legal_moves), [0; 64]);
+
+        move_history.update(&board, D3, 2);
+        move_history.update(&board, C4, 10);
+        let bonus = move_history.bonus(&board, legal_moves);
+        // C4: 直近のキラー手で、ヒストリーが最大
+        assert_eq!(bonus[C4 as usize], KILLER_BONUS[0] + HISTORY_BONUS_MAX);
+        assert!(bonus[D3 as usize] > bonus[F5 as usize]);
+        assert!(bonus.iter().all(|&b| b < MOVE_HISTORY_SCALE));
+
+        // 相手の手番の局面には、手番側のヒストリーは影響しない
+        let mut next_board = board.clone();
+        next_board.put_piece_fast(1 << C4);
+        assert_eq!(move_history.bonus(&next_board, next_board.put_able()), [0; 64]);
+
+        // 無効な場合は、記録も加点もしない
+        let mut move_history = MoveHistory::new(false);
+        move_history.update(&board, D3, 10);
+        assert_eq!(move_history.bonus(&board, legal_moves), [0; 64]);
+    }
+}
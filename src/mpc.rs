@@ -1,3 +1,10 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
 use crate::{
     board::*,
     search::*,
@@ -66,14 +73,92 @@ pub const SELECTIVITY: [Selectivity;N_SELECTIVITY_LV] =
     },
 ];
 
+/// MPCのパラメータ
+///
+/// 深い探索の評価値`V`を、浅い探索(深さ`lv`)の評価値`v`から、`V = a * v + b + e`と推定する。
+/// `e`は、標準偏差が`e_std`の誤差である。
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 pub struct MpcParams {
     pub lv    : i32,
-        a     : f64,
-        b     : f64,
-        e_std : f64
+    pub a     : f64,
+    pub b     : f64,
+    pub e_std : f64
+}
+
+/// 実行時に読み込むMPCのパラメータ (`mpc_tool`で統計から求める)
+///
+/// 既定では`MpcTable::DEFAULT_FILE_PATH`から読み込む。(`MpcTable::default_table`)
+/// 値がない(`None`の)場合は、組み込みのパラメータ(`perfect_search_mpc_params`, `gen_eval_search_mpc_params`)を使用する。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct MpcTable {
+    /// 完全読みのMPCのパラメータ (`perfect_search[空きマス数]`)
+    pub perfect_search: Vec<Option<MpcParams>>,
+    /// 評価関数による探索のMPCのパラメータ (`eval_search[探索の深さ][空きマス数]`)
+    pub eval_search: Vec<Vec<Option<MpcParams>>>,
+}
+
+impl MpcTable {
+    /// 完全読みで、空きマス数が`n_empties`の局面に用いるパラメータ
+    #[inline(always)]
+    pub fn perfect_search_params(&self, n_empties: i32) -> Option<MpcParams> {
+        self.perfect_search.get(n_empties as usize).copied().flatten()
+    }
+
+    /// 評価関数による深さ`lv`の探索で、空きマス数が`n_empties`の局面に用いるパラメータ
+    #[inline(always)]
+    pub fn eval_search_params(&self, lv: i32, n_empties: i32) -> Option<MpcParams> {
+        self.eval_search.get(lv as usize).and_then(|t| t.get(n_empties as usize)).copied().flatten()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    pub fn from_json(input: &str) -> io::Result<MpcTable> {
+        serde_json::from_str(input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<MpcTable> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// 既定で読み込むファイル
+    ///
+    /// パラメータは評価関数に依存するため、`res/eval.json`と同じ評価関数で、`mpc`サブコマンドにより求める。
+    pub const DEFAULT_FILE_PATH: &'static str = "res/mpc.json";
+
+    /// `DEFAULT_FILE_PATH`から読み込んだパラメータ (最初の呼び出しでのみ読み込む)
+    ///
+    /// # 戻り値
+    /// * ファイルが存在しないか、読み込めない場合は、`None`
+    pub fn default_table() -> Option<Arc<MpcTable>> {
+        static DEFAULT_TABLE: OnceLock<Option<Arc<MpcTable>>> = OnceLock::new();
+        DEFAULT_TABLE.get_or_init(|| {
+            match Self::read_file(Self::DEFAULT_FILE_PATH) {
+                Ok(table) => Some(Arc::new(table)),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(e) => {
+                    eprintln!("{}: {}", Self::DEFAULT_FILE_PATH, e);
+                    None
+                }
+            }
+        }).clone()
+    }
 }
 
-pub const PERFECT_SEARCH_MPC_SEARCH_PARAMS: [Option<MpcParams>; 61] = [
+/// 組み込みのパラメータ(`PERFECT_SEARCH_MPC_SEARCH_PARAMS`)がある、空きマス数の最大値
+pub const PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES: i32 = 20;
+
+/// 完全読みのMPCのパラメータ (空きマス数ごと)
+///
+/// 完全読みの結果を正解として、統計から求めた値である。
+/// 空きマスが`PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES`より多い局面は、`perfect_search_mpc_params`を参照。
+pub const PERFECT_SEARCH_MPC_SEARCH_PARAMS: [Option<MpcParams>; PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES as usize + 1] = [
     None, // 存在しない
     None, // 残り1マス
     None, // 2
@@ -95,52 +180,30 @@ pub const PERFECT_SEARCH_MPC_SEARCH_PARAMS: [Option<MpcParams>; 61] = [
     Some(MpcParams {lv: 6, a: 1.0281382671934618, b: 0.7327216969440862, e_std: 4.589407020617368 }),
     Some(MpcParams {lv: 5, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
     Some(MpcParams {lv: 6, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), // 20
-    // ここからの、統計的なパラメータの計算はされていない。
-    // 空きマスが20のときの値を流用している。
-    Some(MpcParams {lv: 5, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 6, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 5, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 6, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 5, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 6, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 5, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 6, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
-    Some(MpcParams {lv: 7, a: 1.0316652919636162, b: -1.8229371340268825, e_std: 4.810056307416734 }),
-    Some(MpcParams {lv: 8, a: 1.0368717822009714, b: 0.8963184321993207, e_std: 4.575580119070469 }), 
 ];
 
+/// 完全読みのMPCで、空きマス数ごとの浅い探索の深さ
+///
+/// `mpc_tool`で統計を取る際も、この深さで浅い探索を行う。
+pub const PERFECT_SEARCH_MPC_SEARCH_LV: [i32; 61] = [
+    0,
+    0, 0, 0, 0, 0, 0, 1, 2, 1, 2,
+    3, 4, 3, 4, 3, 4, 5, 6, 5, 6,
+    5, 6, 5, 6, 5, 6, 5, 6, 7, 8,
+    7, 8, 7, 8, 7, 8, 7, 8, 7, 8,
+    7, 8, 7, 8, 7, 8, 7, 8, 7, 8,
+    7, 8, 7, 8, 7, 8, 7, 8, 7, 8
+];
+
+/// 組み込みの、完全読みのMPCのパラメータ
+///
+/// 統計のある、空きマスが`PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES`以下の局面のみ値を返す。
+/// それより空きマスが多い局面は、`mpc_tool`で求めたファイル(`MpcTable::DEFAULT_FILE_PATH`)がある場合のみMPCを行う。
+pub fn perfect_search_mpc_params(n_empties: i32) -> Option<MpcParams> {
+    PERFECT_SEARCH_MPC_SEARCH_PARAMS.get(n_empties as usize).copied().flatten()
+}
+
 
-const MPC_START_LEVEL_EVAL_SEARCH: i32 = 5;
 pub const EVAL_SEARCH_MPC_SEARCH_LV: [i32; 61] = [
     0,
     0, 0, 0, 0, 1, 2, 1, 2, 3, 4,
@@ -151,7 +214,7 @@ pub const EVAL_SEARCH_MPC_SEARCH_LV: [i32; 61] = [
     9, 10, 9, 10, 9, 10, 9, 10, 9, 10
 ];
 
-fn gen_eval_search_mpc_params(lv_i32: i32, n_empties: i32) -> MpcParams
+pub fn gen_eval_search_mpc_params(lv_i32: i32, n_empties: i32) -> MpcParams
 {
     let mpc_lv_i32 = EVAL_SEARCH_MPC_SEARCH_LV[lv_i32 as usize];
     let lv = lv_i32 as f64;
@@ -188,11 +251,14 @@ pub fn eval_search_mpc(
     search         : &mut Search
 ) -> ProbCutResult
 {
-    if lv < MPC_START_LEVEL_EVAL_SEARCH {
+    if lv < search.config.mpc_start_level_eval_search {
         return  ProbCutResult::FAIL;
     }
     let n_empties = board.empties_count();
-    let mpc_params = gen_eval_search_mpc_params(lv, n_empties);
+    let mpc_params = match search.config.mpc_table.as_ref().and_then(|t| t.eval_search_params(lv, n_empties)) {
+        Some(params) => params,
+        None => gen_eval_search_mpc_params(lv, n_empties)
+    };
 
     multi_prob_cut(board, alpha, beta, &mpc_params, search)
 }
@@ -206,11 +272,14 @@ pub fn perfect_search_mpc(
 ) -> ProbCutResult
 {
     let n_empties = board.empties_count();
-    let mpc_params = match &PERFECT_SEARCH_MPC_SEARCH_PARAMS[n_empties as usize] {
-        Some(params) => { params },
-        None                     => { return ProbCutResult::FAIL }
+    let mpc_params = match search.config.mpc_table.as_ref().and_then(|t| t.perfect_search_params(n_empties)) {
+        Some(params) => params,
+        None => match perfect_search_mpc_params(n_empties) {
+            Some(params) => params,
+            None         => { return ProbCutResult::FAIL }
+        }
     };
-    multi_prob_cut(board, alpha, beta, mpc_params, search)
+    multi_prob_cut(board, alpha, beta, &mpc_params, search)
 }
 
 #[inline(always)]
@@ -279,4 +348,32 @@ fn nws_eval_0_selectivity_lv(board: &Board, alpha: i32, lv: i32, search : &mut S
     let score = nws_eval(board, alpha, lv, search);
     search.selectivity_lv = main_search_selectivity_lv;
     score
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perfect_search_mpc_params_only_for_measured_empties() {
+        for n_empties in 0..=60 {
+            let params = perfect_search_mpc_params(n_empties);
+            assert_eq!(params.is_some(), (5..=PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES).contains(&n_empties), "{}", n_empties);
+            if let Some(params) = params {
+                assert_eq!(params.lv, PERFECT_SEARCH_MPC_SEARCH_LV[n_empties as usize], "{}", n_empties);
+            }
+        }
+    }
+
+    #[test]
+    fn mpc_table_overrides_builtin_params() {
+        let params = MpcParams { lv: 4, a: 1.0, b: 0.0, e_std: 3.0 };
+        let mut perfect_search = vec![None; 31];
+        perfect_search[30] = Some(params);
+        let table = MpcTable { perfect_search, ..Default::default() };
+        assert_eq!(table.perfect_search_params(30), Some(params));
+        assert_eq!(table.perfect_search_params(29), None);
+        assert_eq!(table.perfect_search_params(60), None);
+        assert_eq!(MpcTable::from_json(&table.to_json()).unwrap(), table);
+    }
+}
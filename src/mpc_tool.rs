@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::board::*;
+use crate::book::*;
+use crate::eval::*;
+use crate::eval_search::*;
+use crate::evaluator_const::*;
+use crate::mpc::*;
+use crate::search::*;
+use crate::search_observer::NullObserver;
+use crate::search_config::*;
+use crate::solver::*;
+use crate::t_table::*;
+use crate::tune_tool::random_positions;
+
+/// 統計に使用する、浅い探索の深さの最大値 (これより深い探索は、統計を取る時間がかかりすぎる)
+const MAX_MPC_SEARCH_LV: i32 = 10;
+
+/// MPCの統計の1サンプル
+///
+/// 深い探索の評価値`score`(`V`)と、浅い探索の評価値`mpc_score`(`v`)の組
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MpcSample {
+    pub n_empties: i32,
+    /// 深い探索の深さ (完全読みの場合は空きマス数)
+    pub lv: i32,
+    /// 浅い探索の深さ
+    pub mpc_lv: i32,
+    pub score: i32,
+    pub mpc_score: i32,
+}
+
+/// MPCの統計を取る設定
+pub struct MpcStatsConfig {
+    /// 評価関数による探索の統計を取る、深い探索の深さの範囲
+    pub eval_lvs: std::ops::RangeInclusive<i32>,
+    /// 完全読みの統計を取る、空きマス数の範囲 (完全読みの結果を正解とする)
+    pub perfect_empties: std::ops::RangeInclusive<i32>,
+    /// 完全読みのMPCの統計を、深い探索(`deep_lv`)の結果を正解として取る、空きマス数の範囲
+    ///
+    /// 完全読みが現実的な時間で終わらない局面に使用する。
+    pub deep_empties: Option<std::ops::RangeInclusive<i32>>,
+    /// `deep_empties`の統計で、正解とする探索の深さ
+    pub deep_lv: i32,
+    /// パラメータを求めるのに必要な、サンプル数の最小値
+    pub min_samples: usize,
+}
+
+impl Default for MpcStatsConfig {
+    fn default() -> Self {
+        Self {
+            eval_lvs: SearchConfig::default().mpc_start_level_eval_search..=12,
+            perfect_empties: 5..=PERFECT_SEARCH_MPC_MAX_MEASURED_EMPTIES,
+            deep_empties: None,
+            deep_lv: 16,
+            min_samples: 30,
+        }
+    }
+}
+
+/// 棋譜を再生し、空きマスが`empties`の範囲にある、手番側に合法手がある局面を集める。
+///
+/// 空きマス数ごとに、最大`max_per_empties`局面とする。
+pub fn game_record_positions(games: &[Vec<u8>], empties: std::ops::RangeInclusive<i32>, max_per_empties: usize) -> Vec<Board> {
+    let mut counts = [0usize; 61];
+    let mut boards = Vec::new();
+    for moves in games.iter() {
+        let mut board = Board::new();
+        for &put_place in moves.iter() {
+            board = Book::node_board(&board);
+            if put_place >= 64 || board.put_able() & (1 << put_place) == 0 {
+                break;
+            }
+            board.put_piece_fast(1 << put_place);
+
+            let n_empties = board.empties_count();
+            if empties.contains(&n_empties) && board.put_able() != 0 && counts[n_empties as usize] < max_per_empties {
+                counts[n_empties as usize] += 1;
+                boards.push(board.clone());
+            }
+        }
+    }
+    boards
+}
+
+/// 評価関数による探索のMPCの統計を取る。
+///
+/// 各局面で、`eval_lvs`の深さの探索と、`EVAL_SEARCH_MPC_SEARCH_LV`の深さの浅い探索を、MPCなしで行う。
+/// 局面ごとに置換表を初期化するため、結果は局面の順序に依存しない。
+pub fn collect_eval_search_samples(boards: &[Board], eval_lvs: std::ops::RangeInclusive<i32>, evaluator: &mut Evaluator) -> Vec<MpcSample> {
+    let mut samples = Vec::new();
+    for board in boards.iter() {
+        let n_empties = board.empties_count();
+        let mut t_table = TranspositionTable::new();
+        for lv in eval_lvs.clone() {
+            let mpc_lv = EVAL_SEARCH_MPC_SEARCH_LV[lv as usize];
+            // 深い探索が終局まで届く局面は、MPCの対象外
+            if lv >= n_empties || mpc_lv > MAX_MPC_SEARCH_LV {
+                continue;
+            }
+            let mut search = Search::new(board, NO_MPC, &mut t_table, evaluator);
+            let mpc_score = pvs_eval(board, -SCORE_INF, SCORE_INF, mpc_lv, &mut search);
+            let score = pvs_eval(board, -SCORE_INF, SCORE_INF, lv, &mut search);
+            samples.push(MpcSample { n_empties, lv, mpc_lv, score, mpc_score });
+        }
+    }
+    samples
+}
+
+/// 完全読みのMPCの統計を取る。
+///
+/// 各局面で、MPCなしの完全読みの結果と、`PERFECT_SEARCH_MPC_SEARCH_LV`の深さの浅い探索を行う。
+/// 空きマスが`perfect_empties`の範囲外の局面と、MPCを行わない局面は無視する。
+pub fn collect_perfect_search_samples(boards: &[Board], perfect_empties: std::ops::RangeInclusive<i32>, evaluator: &mut Evaluator) -> Vec<MpcSample> {
+    let mut samples = Vec::new();
+    for board in boards.iter() {
+        let n_empties = board.empties_count();
+        if !perfect_empties.contains(&n_empties) {
+            continue;
+        }
+        if perfect_search_mpc_params(n_empties).is_none() {
+            continue;
+        }
+        let mut t_table = TranspositionTable::new();
+        let Ok(result) = perfect_solver_with_observer(board, NO_MPC, &mut t_table, evaluator, &SearchConfig::default(), &mut NullObserver) else {continue};
+
+        let mpc_lv = PERFECT_SEARCH_MPC_SEARCH_LV[n_empties as usize];
+        let mut t_table = TranspositionTable::new();
+        let mut search = Search::new(board, NO_MPC, &mut t_table, evaluator);
+        let mpc_score = pvs_eval(board, -SCORE_INF, SCORE_INF, mpc_lv, &mut search);
+        samples.push(MpcSample { n_empties, lv: n_empties, mpc_lv, score: result.eval, mpc_score });
+    }
+    samples
+}
+
+/// 完全読みのMPCの統計を、完全読みの代わりに深さ`deep_lv`の探索の結果を正解として取る。
+///
+/// 空きマスが多く、完全読みの結果を得られない局面に使用する。
+/// 深い探索自体の誤差は含まれないため、`e_std`は完全読みを正解とした場合より小さくなる傾向がある。
+/// 空きマスが`deep_empties`の範囲外の局面と、`deep_lv`以下の局面は無視する。
+pub fn collect_deep_search_samples(boards: &[Board], deep_empties: std::ops::RangeInclusive<i32>, deep_lv: i32, evaluator: &mut Evaluator) -> Vec<MpcSample> {
+    let mut samples = Vec::new();
+    for board in boards.iter() {
+        let n_empties = board.empties_count();
+        if !deep_empties.contains(&n_empties) || n_empties <= deep_lv {
+            continue;
+        }
+        let mpc_lv = PERFECT_SEARCH_MPC_SEARCH_LV[n_empties as usize];
+        let mut t_table = TranspositionTable::new();
+        let mut search = Search::new(board, NO_MPC, &mut t_table, evaluator);
+        let mpc_score = pvs_eval(board, -SCORE_INF, SCORE_INF, mpc_lv, &mut search);
+        let score = pvs_eval(board, -SCORE_INF, SCORE_INF, deep_lv, &mut search);
+        samples.push(MpcSample { n_empties, lv: deep_lv, mpc_lv, score, mpc_score });
+    }
+    samples
+}
+
+/// 最小二乗法により、`V = a * v + b + e`の`a`, `b`と、誤差`e`の標準偏差を求める。
+///
+/// `samples`は`(v, V)`の組。
+/// サンプルが2つ未満の場合や、`v`が全て等しい場合は`None`を返す。
+pub fn fit_mpc_params(mpc_lv: i32, samples: &[(i32, i32)]) -> Option<MpcParams> {
+    let n = samples.len() as f64;
+    if samples.len() < 2 {
+        return None;
+    }
+    let mean_v = samples.iter().map(|&(v, _)| v as f64).sum::<f64>() / n;
+    let mean_big_v = samples.iter().map(|&(_, big_v)| big_v as f64).sum::<f64>() / n;
+    let var_v = samples.iter().map(|&(v, _)| (v as f64 - mean_v).powi(2)).sum::<f64>();
+    let cov = samples.iter().map(|&(v, big_v)| (v as f64 - mean_v) * (big_v as f64 - mean_big_v)).sum::<f64>();
+    if var_v == 0.0 {
+        return None;
+    }
+
+    let a = cov / var_v;
+    let b = mean_big_v - a * mean_v;
+    // 残差の標準偏差 (不偏分散)
+    let e_var = samples.iter()
+        .map(|&(v, big_v)| (big_v as f64 - (a * v as f64 + b)).powi(2))
+        .sum::<f64>() / (n - 1.0);
+
+    Some(MpcParams { lv: mpc_lv, a, b, e_std: e_var.sqrt() })
+}
+
+/// `fit_mpc_table`で、1組のパラメータを求めるサンプルのまとまり
+///
+/// 順序は、フィールドの順に比較したもの (`fit_mpc_table`は、この順に処理する)
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct SampleGroup {
+    is_perfect: bool,
+    lv: i32,
+    n_empties: i32,
+    mpc_lv: i32,
+}
+
+/// 同じ(深さ, 空きマス数)のサンプルごとにパラメータを求め、`MpcTable`を作る。
+///
+/// 完全読みのサンプル(`collect_deep_search_samples`のサンプルを含む)は空きマス数ごと、
+/// 評価関数による探索のサンプルは(深さ, 空きマス数)ごとにまとめる。
+/// 同じ空きマス数に、完全読みの結果と深い探索の結果を正解とするサンプルがある場合は、完全読みの結果を優先する。
+/// サンプル数が`min_samples`未満の組は、`None`(組み込みのパラメータを使用)とする。
+pub fn fit_mpc_table(eval_samples: &[MpcSample], perfect_samples: &[MpcSample], min_samples: usize) -> MpcTable {
+    let mut groups: BTreeMap<SampleGroup, Vec<(i32, i32)>> = BTreeMap::new();
+    for (is_perfect, samples) in [(false, eval_samples), (true, perfect_samples)] {
+        for s in samples.iter() {
+            let group = SampleGroup { is_perfect, lv: s.lv, n_empties: s.n_empties, mpc_lv: s.mpc_lv };
+            groups.entry(group).or_default().push((s.mpc_score, s.score));
+        }
+    }
+
+    // 完全読みのサンプルは、深い探索の深さ(`lv`)の昇順に処理されるため、完全読み(`lv == n_empties`)の結果が最後に書き込まれる
+    let mut table = MpcTable::default();
+    for (SampleGroup { is_perfect, lv, n_empties, mpc_lv }, samples) in groups {
+        if samples.len() < min_samples.max(2) {
+            continue;
+        }
+        let Some(params) = fit_mpc_params(mpc_lv, &samples) else {continue};
+        let row = if is_perfect {
+            &mut table.perfect_search
+        } else {
+            if table.eval_search.len() <= lv as usize {
+                table.eval_search.resize(lv as usize + 1, Vec::new());
+            }
+            &mut table.eval_search[lv as usize]
+        };
+        if row.len() <= n_empties as usize {
+            row.resize(n_empties as usize + 1, None);
+        }
+        row[n_empties as usize] = Some(params);
+    }
+    table
+}
+
+/// サンプルをCSVで保存する。 (`kind`は`eval`, `perfect`, または深い探索を正解とする`deep`)
+pub fn write_samples_csv<P: AsRef<Path>>(path: P, eval_samples: &[MpcSample], perfect_samples: &[MpcSample]) -> io::Result<()> {
+    let mut csv = String::from("kind,n_empties,lv,mpc_lv,score,mpc_score\n");
+    for (kind, samples) in [("eval", eval_samples), ("perfect", perfect_samples)] {
+        for s in samples.iter() {
+            let kind = if kind == "perfect" && s.lv != s.n_empties {"deep"} else {kind};
+            csv += &format!("{},{},{},{},{},{}\n", kind, s.n_empties, s.lv, s.mpc_lv, s.score, s.mpc_score);
+        }
+    }
+    fs::write(path, csv)
+}
+
+fn parse_range(s: &str) -> Result<std::ops::RangeInclusive<i32>, String> {
+    let parse = |x: &str| x.trim().parse::<i32>().map_err(|_| format!("invalid range: {}", s));
+    match s.split_once('-') {
+        Some((min, max)) => Ok(parse(min)?..=parse(max)?),
+        None => {
+            let x = parse(s)?;
+            Ok(x..=x)
+        }
+    }
+}
+
+/// コマンドラインから、MPCの統計を取り、パラメータを求める。
+///
+/// ```text
+/// deft_reversi_engine mpc [--games games.ggf ...] [--random 200 --seed 0] [--per-empties 200]
+///                         [--eval-lv 5-12] [--perfect-empties 5-20] [--deep-empties 21-60 --deep-lv 16]
+///                         [--min-samples 30] [--samples samples.csv] --output mpc.json
+/// ```
+///
+/// 局面は、棋譜(`--games`)と、空きマス数ごとにランダムに生成した局面(`--random`)から集める。
+/// `--deep-empties`を指定した場合は、完全読みの代わりに深さ`--deep-lv`の探索を正解として、
+/// その範囲の空きマス数の、完全読みのMPCのパラメータを求める。
+/// 求めたパラメータは、`res/mpc.json`(`MpcTable::DEFAULT_FILE_PATH`)に置くと既定で使用される。
+/// 他のファイルは、`SearchConfig::mpc_file`に指定して使用する。
+pub fn mpc_cli(args: &[String]) -> Result<(), String> {
+    let mut stats_config = MpcStatsConfig::default();
+    let mut game_files = Vec::new();
+    let mut n_random = 0;
+    let mut seed = 0;
+    let mut per_empties = 200;
+    let mut samples_path = None;
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--games"           => game_files.push(value()?.clone()),
+            "--random"          => n_random = value()?.parse().map_err(|_| "invalid number of positions")?,
+            "--seed"            => seed = value()?.parse().map_err(|_| "invalid seed")?,
+            "--per-empties"     => per_empties = value()?.parse().map_err(|_| "invalid number of positions")?,
+            "--eval-lv"         => stats_config.eval_lvs = parse_range(value()?)?,
+            "--perfect-empties" => stats_config.perfect_empties = parse_range(value()?)?,
+            "--deep-empties"    => stats_config.deep_empties = Some(parse_range(value()?)?),
+            "--deep-lv"         => stats_config.deep_lv = value()?.parse().map_err(|_| "invalid deep-lv")?,
+            "--min-samples"     => stats_config.min_samples = value()?.parse().map_err(|_| "invalid number of samples")?,
+            "--samples"         => samples_path = Some(value()?.clone()),
+            "--output"          => output = Some(value()?.clone()),
+            _ => return Err(format!("unknown option: {}", arg))
+        }
+    }
+    let output = output.ok_or("missing --output")?;
+    if *stats_config.eval_lvs.start() < 1 || *stats_config.eval_lvs.end() > 60 {
+        return Err("eval-lv must be 1 ~ 60".to_string());
+    }
+    if *stats_config.perfect_empties.start() < 1 || *stats_config.perfect_empties.end() > 60 {
+        return Err("perfect-empties must be 1 ~ 60".to_string());
+    }
+    if let Some(deep_empties) = &stats_config.deep_empties {
+        if *deep_empties.start() < 1 || *deep_empties.end() > 60 {
+            return Err("deep-empties must be 1 ~ 60".to_string());
+        }
+        if stats_config.deep_lv < 1 || stats_config.deep_lv >= *deep_empties.start() {
+            return Err("deep-lv must be 1 ~ (deep-empties - 1)".to_string());
+        }
+    }
+
+    // 評価関数による探索は、深い探索が終局まで届かない局面を使用する
+    let eval_empties = (*stats_config.eval_lvs.start() + 1)..=60;
+    let mut all_empties = *stats_config.perfect_empties.start().min(eval_empties.start())..=60;
+    if let Some(deep_empties) = &stats_config.deep_empties {
+        all_empties = *all_empties.start().min(deep_empties.start())..=60;
+    }
+
+    let mut boards = Vec::new();
+    for path in game_files.iter() {
+        let games = read_game_records(path).map_err(|e| format!("{}: {}", path, e))?;
+        boards.extend(game_record_positions(&games, all_empties.clone(), per_empties));
+    }
+    for n_empties in all_empties.clone() {
+        boards.extend(random_positions(n_random, n_empties, seed + n_empties as u64)?);
+    }
+    if boards.is_empty() {
+        return Err("no positions (use --games or --random)".to_string());
+    }
+
+    let mut evaluator = Evaluator::read_file().map_err(|e| e.to_string())?;
+
+    let eval_boards: Vec<Board> = boards.iter().filter(|b| eval_empties.contains(&b.empties_count())).cloned().collect();
+    println!("eval search: {} positions", eval_boards.len());
+    let eval_samples = collect_eval_search_samples(&eval_boards, stats_config.eval_lvs.clone(), &mut evaluator);
+
+    let perfect_boards: Vec<Board> = boards.iter().filter(|b| stats_config.perfect_empties.contains(&b.empties_count())).cloned().collect();
+    println!("perfect search: {} positions", perfect_boards.len());
+    let mut perfect_samples = collect_perfect_search_samples(&perfect_boards, stats_config.perfect_empties.clone(), &mut evaluator);
+
+    if let Some(deep_empties) = &stats_config.deep_empties {
+        let deep_boards: Vec<Board> = boards.iter().filter(|b| deep_empties.contains(&b.empties_count())).cloned().collect();
+        println!("deep search (lv {}): {} positions", stats_config.deep_lv, deep_boards.len());
+        perfect_samples.extend(collect_deep_search_samples(&deep_boards, deep_empties.clone(), stats_config.deep_lv, &mut evaluator));
+    }
+
+    if let Some(path) = samples_path {
+        write_samples_csv(&path, &eval_samples, &perfect_samples).map_err(|e| format!("{}: {}", path, e))?;
+        println!("saved: {}", path);
+    }
+
+    let table = fit_mpc_table(&eval_samples, &perfect_samples, stats_config.min_samples);
+    for (n_empties, params) in table.perfect_search.iter().enumerate() {
+        if let Some(p) = params {
+            println!("perfect {:2} empties: lv {:2}, a {:.4}, b {:+.4}, e_std {:.4}", n_empties, p.lv, p.a, p.b, p.e_std);
+        }
+    }
+    let n_eval_params = table.eval_search.iter().flatten().filter(|p| p.is_some()).count();
+    println!("eval search: {} parameters", n_eval_params);
+
+    table.write_file(&output).map_err(|e| format!("{}: {}", output, e))?;
+    println!("saved: {}", output);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_recovers_linear_relation() {
+        // V = 1.5 * v - 2 ± 1
+        let samples: Vec<(i32, i32)> = (-10..=10)
+            .flat_map(|v| [(v, (1.5 * v as f64) as i32 - 2 + 1), (v, (1.5 * v as f64) as i32 - 2 - 1)])
+            .filter(|&(v, _)| v % 2 == 0)
+            .collect();
+        let params = fit_mpc_params(3, &samples).unwrap();
+        assert_eq!(params.lv, 3);
+        assert!((params.a - 1.5).abs() < 1e-9);
+        assert!((params.b + 2.0).abs() < 1e-9);
+        let n = samples.len() as f64;
+        assert!((params.e_std - (n / (n - 1.0)).sqrt()).abs() < 1e-9);
+
+        assert!(fit_mpc_params(3, &[(1, 2)]).is_none());
+        assert!(fit_mpc_params(3, &[(1, 2), (1, 3)]).is_none());
+    }
+
+    #[test]
+    fn fitted_table_is_grouped_by_depth_and_empties() {
+        let sample = |n_empties, lv, mpc_score, score| MpcSample { n_empties, lv, mpc_lv: 2, score, mpc_score };
+        let eval_samples = [sample(30, 6, 0, 1), sample(30, 6, 2, 3), sample(30, 6, 4, 5), sample(31, 6, 0, 0)];
+        // 空きマス12は完全読みの結果を優先し、空きマス24は深い探索(深さ16)の結果を使用する
+        let perfect_samples = [
+            sample(12, 12, 0, 0), sample(12, 12, 1, 2), sample(12, 12, 2, 4),
+            sample(12, 8, 0, 0), sample(12, 8, 1, 3), sample(12, 8, 2, 6),
+            sample(24, 16, 0, 1), sample(24, 16, 1, 4), sample(24, 16, 2, 7),
+        ];
+        let table = fit_mpc_table(&eval_samples, &perfect_samples, 3);
+
+        let params = table.eval_search_params(6, 30).unwrap();
+        assert!((params.a - 1.0).abs() < 1e-9 && (params.b - 1.0).abs() < 1e-9);
+        assert!(table.eval_search_params(6, 31).is_none());
+        assert!(table.eval_search_params(7, 30).is_none());
+        assert!((table.perfect_search_params(12).unwrap().a - 2.0).abs() < 1e-9);
+        assert!(table.perfect_search_params(13).is_none());
+        assert!((table.perfect_search_params(24).unwrap().a - 3.0).abs() < 1e-9);
+
+        assert_eq!(MpcTable::from_json(&table.to_json()).unwrap(), table);
+    }
+
+    #[test]
+    fn game_record_positions_are_limited_per_empties() {
+        let games = vec![vec![F5, D6, C3, D3, C4], vec![F5, F6, E6], vec![F5, D6, A1]];
+        let boards = game_record_positions(&games, 56..=58, 1);
+        let empties: Vec<i32> = boards.iter().map(|b| b.empties_count()).collect();
+        assert_eq!(empties, vec![58, 57, 56]);
+    }
+}
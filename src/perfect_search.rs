@@ -6,36 +6,6 @@ use crate::mpc::*;
 
 const SCORE_INF: i32 = i8::MAX as i32;
 
-/// 空きマスが残り`SWITCH_EMPTIES_SIMPLE_PVS`以下である場合、
-/// `pvs_perfect`から、`pvs_perfect_simple`へ切り替える
-const SWITCH_EMPTIES_SIMPLE_PVS: i32 = 8;
-
-/// 空きマスが残り`SWITCH_EMPTIES_SIMPLE_NWS`以下である場合、
-/// `nws_perfect`から、`nws_perfect_simple`へ切り替える
-const SWITCH_EMPTIES_SIMPLE_NWS: i32 = 8;
-
-/// 空きマスが残り`SWITCH_EMPTIES_NEGA_ALPHA`以下である場合、
-/// `pvs_perfect_simple`や`nws_perfect_simple`から、`negaalpha_perfect`へ切り替える
-const SWITCH_EMPTIES_NEGA_ALPHA: i32 = 6;
-
-/// `pvs_perfect`, `nws_perfect`でのmove orderingにおいて、
-/// 空きマスが`SWITCH_EMPTIES_MOVE_ORDER`より多い場合、
-/// 評価関数とNegascout探索を用いた`move_ordering_eval`を使用する。
-/// SWITCH_EMPTIES_MOVE_ORDER以下である場合は、`move_ordering_ffs`を使用する。
-const SWITCH_EMPTIES_MOVE_ORDER: i32 = 14;
-
-/// `pvs_perfect`, `nws_perfect`でのmove orderingにおいて、評価関数とalpha-beta探索を用いた`move_ordering_eval`を使用する場合の、探索の深さ
-const MOVE_ORDERING_EVAL_LEVEL_T: [i32; 61] = [
-    0,
-    0,  0,  0,  0,  0,  0,  0,  0,  1,  1,
-    1,  1,  2,  2,  2,  2,  2,  2,  2,  2,
-    2,  2,  3,  3,  3,  3,  3,  3,  3,  3,
-    3,  3,  3,  3,  3,  3,  3,  3,  4,  4,
-    4,  4,  4,  4,  4,  4,  4,  4,  4,  4,
-    4,  4,  4,  4,  4,  4,  4,  4,  4,  4,
-];
-
-
 /// オセロの盤面に基づいて最終スコアを計算
 ///
 /// この関数は、現在のプレイヤーと対戦相手の石の数に基づいて、
@@ -50,9 +20,12 @@ const MOVE_ORDERING_EVAL_LEVEL_T: [i32; 61] = [
 ///
 /// # 例
 /// ```
-/// let board = Board::new(); // ゲーム終了時の盤面を生成
+/// use deft_reversi_engine::board::Board;
+/// use deft_reversi_engine::perfect_search::solve_score;
+///
+/// let board = Board::new(); // 初期盤面 (石数が等しいため、スコアは0)
 /// let score = solve_score(&board);
-/// println!("Final Score: {}", score);
+/// assert_eq!(score, 0);
 /// ```
 ///
 /// # 注記
@@ -60,8 +33,14 @@ const MOVE_ORDERING_EVAL_LEVEL_T: [i32; 61] = [
 /// * 空きマスが存在する場合、それらを勝っている側のスコアに加算する。
 #[inline(always)]
 pub fn solve_score(board: &Board) -> i32 {
-    let n_player: i32 = board.bit_board[board.next_turn].count_ones() as i32;
-    let n_opponent: i32 = board.bit_board[board.next_turn^1].count_ones() as i32;
+    solve_score_bits(board.bit_board[board.next_turn], board.bit_board[board.next_turn ^ 1])
+}
+
+/// 手番側の石`p`と相手側の石`o`から、最終スコアを計算する。(`solve_score`を参照)
+#[inline(always)]
+fn solve_score_bits(p: u64, o: u64) -> i32 {
+    let n_player: i32 = p.count_ones() as i32;
+    let n_opponent: i32 = o.count_ones() as i32;
     let diff: i32 = n_player - n_opponent;
 
     // https://github.com/rust-lang/rust-clippy/issues/5354
@@ -88,9 +67,13 @@ pub fn solve_score(board: &Board) -> i32 {
 ///
 /// # 例
 /// ```
-/// let board = Board::new(); // 空きマスがないゲーム終了時の盤面を生成
+/// use deft_reversi_engine::board::Board;
+/// use deft_reversi_engine::perfect_search::solve_score_0_empties;
+///
+/// // 空きマスがないゲーム終了時の盤面 (黒40石、白24石)
+/// let board = Board::from_bit_board([u64::MAX << 24, (1 << 24) - 1], Board::BLACK);
 /// let score = solve_score_0_empties(&board);
-/// println!("Final Score: {}", score);
+/// assert_eq!(score, 16);
 /// ```
 ///
 /// # 注記
@@ -106,6 +89,204 @@ pub fn solve_score_0_empties(board: &Board) -> i32
     2 * (board.bit_board[board.next_turn].count_ones() as i32) - 64
 }
 
+/// 空きマスが残り4マス以下の盤面を、完全読みする。
+///
+/// `negaalpha_perfect`とは異なり、盤面の複製や合法手の生成を行わず、
+/// 空きマスを順に調べ、裏返る石がある場合のみ着手する。
+/// 空きマスは、空きマスが奇数個の領域(盤面を4分割した領域)にあるマスから順に調べる。(偶数理論)
+///
+/// # 引数
+/// * `board` - 空きマスが4マス以下の盤面
+/// * `alpha` - 探索の下限値を示すアルファ値。
+/// * `beta` - 探索の上限値を示すベータ値。
+///
+/// # 戻り値
+/// * 探索結果として計算された盤面のスコア。(`negaalpha_perfect`と同様に、fail-soft)
+pub fn solve_last_moves(board: &Board, alpha: i32, beta: i32, search: &mut Search) -> i32
+{
+    let p = board.bit_board[board.next_turn];
+    let o = board.bit_board[board.next_turn ^ 1];
+    let empties = !(p | o);
+
+    #[cfg(debug_assertions)]
+    assert!(empties.count_ones() <= 4);
+
+    // 偶数理論: 空きマスが奇数個の領域のマスを先に調べる
+    let odd_region = odd_parity_region(empties);
+    let mut squares = [0u8; 4];
+    let mut n_squares = 0;
+    for mut bits in [empties & odd_region, empties & !odd_region] {
+        while bits != 0 {
+            squares[n_squares] = bits.trailing_zeros() as u8;
+            bits &= bits - 1;
+            n_squares += 1;
+        }
+    }
+
+    match n_squares {
+        0 => {
+            search.perfect_search_node_count += 1;
+            search.perfect_search_leaf_node_count += 1;
+            solve_score_0_empties(board)
+        },
+        1 => solve_1_empty(p, o, squares[0], search),
+        2 => solve_2_empties(p, o, alpha, beta, [squares[0], squares[1]], false, search),
+        3 => solve_3_empties(p, o, alpha, beta, [squares[0], squares[1], squares[2]], false, search),
+        _ => solve_4_empties(p, o, alpha, beta, squares, false, search),
+    }
+}
+
+/// 残り1マスの盤面の最終スコアを求める。
+///
+/// 最後の1手は、裏返る石の数のみから、最終スコアを計算する。
+#[inline(always)]
+fn solve_1_empty(p: u64, o: u64, x: u8, search: &mut Search) -> i32
+{
+    search.perfect_search_node_count += 1;
+    search.perfect_search_leaf_node_count += 1;
+
+    let put_place = 1u64 << x;
+    let n_player = p.count_ones() as i32;
+
+    let n_flips = flip_bit(p, o, put_place).count_ones() as i32;
+    if n_flips != 0 {
+        return 2 * (n_player + 1 + n_flips) - 64;
+    }
+
+    // パスして、相手が最後の1手を打つ
+    let n_flips = flip_bit(o, p, put_place).count_ones() as i32;
+    if n_flips != 0 {
+        return 2 * (n_player - n_flips) - 64;
+    }
+
+    // どちらも置けない: 空きマスは勝っている側に加算する (石の数の差は奇数のため、引き分けはない)
+    let diff = 2 * n_player - 63;
+    if diff > 0 {diff + 1} else {diff - 1}
+}
+
+/// 残り2マスの盤面を完全読みする。
+///
+/// # 引数
+/// * `squares` - 空きマス (この順に着手を調べる)
+/// * `passed` - 直前にパスをしたか (両者とも置けない場合は、終局)
+fn solve_2_empties(p: u64, o: u64, mut alpha: i32, beta: i32, squares: [u8; 2], passed: bool, search: &mut Search) -> i32
+{
+    search.perfect_search_node_count += 1;
+
+    let mut best_score = -SCORE_INF;
+    for (x, last) in [(squares[0], squares[1]), (squares[1], squares[0])] {
+        let put_place = 1u64 << x;
+        let flip = flip_bit(p, o, put_place);
+        if flip == 0 {continue;}
+
+        let score = -solve_1_empty(o ^ flip, p ^ flip ^ put_place, last, search);
+        if score >= beta {
+            return score;
+        }
+        if score > alpha {alpha = score;}
+        if score > best_score {best_score = score;}
+    }
+
+    if best_score == -SCORE_INF {
+        if passed {
+            search.perfect_search_leaf_node_count += 1;
+            return solve_score_bits(p, o);
+        }
+        return -solve_2_empties(o, p, -beta, -alpha, squares, true, search);
+    }
+
+    best_score
+}
+
+/// 残り3マスの盤面を完全読みする。(`solve_2_empties`を参照)
+fn solve_3_empties(p: u64, o: u64, mut alpha: i32, beta: i32, squares: [u8; 3], passed: bool, search: &mut Search) -> i32
+{
+    search.perfect_search_node_count += 1;
+
+    let mut best_score = -SCORE_INF;
+    for (x, rest) in [
+        (squares[0], [squares[1], squares[2]]),
+        (squares[1], [squares[0], squares[2]]),
+        (squares[2], [squares[0], squares[1]]),
+    ] {
+        let put_place = 1u64 << x;
+        let flip = flip_bit(p, o, put_place);
+        if flip == 0 {continue;}
+
+        let score = -solve_2_empties(o ^ flip, p ^ flip ^ put_place, -beta, -alpha, rest, false, search);
+        if score >= beta {
+            return score;
+        }
+        if score > alpha {alpha = score;}
+        if score > best_score {best_score = score;}
+    }
+
+    if best_score == -SCORE_INF {
+        if passed {
+            search.perfect_search_leaf_node_count += 1;
+            return solve_score_bits(p, o);
+        }
+        return -solve_3_empties(o, p, -beta, -alpha, squares, true, search);
+    }
+
+    best_score
+}
+
+/// 残り4マスの盤面を完全読みする。(`solve_2_empties`を参照)
+fn solve_4_empties(p: u64, o: u64, mut alpha: i32, beta: i32, squares: [u8; 4], passed: bool, search: &mut Search) -> i32
+{
+    search.perfect_search_node_count += 1;
+
+    let mut best_score = -SCORE_INF;
+    for (x, rest) in [
+        (squares[0], [squares[1], squares[2], squares[3]]),
+        (squares[1], [squares[0], squares[2], squares[3]]),
+        (squares[2], [squares[0], squares[1], squares[3]]),
+        (squares[3], [squares[0], squares[1], squares[2]]),
+    ] {
+        let put_place = 1u64 << x;
+        let flip = flip_bit(p, o, put_place);
+        if flip == 0 {continue;}
+
+        let score = -solve_3_empties(o ^ flip, p ^ flip ^ put_place, -beta, -alpha, rest, false, search);
+        if score >= beta {
+            return score;
+        }
+        if score > alpha {alpha = score;}
+        if score > best_score {best_score = score;}
+    }
+
+    if best_score == -SCORE_INF {
+        if passed {
+            search.perfect_search_leaf_node_count += 1;
+            return solve_score_bits(p, o);
+        }
+        return -solve_4_empties(o, p, -beta, -alpha, squares, true, search);
+    }
+
+    best_score
+}
+
+/// 相手の確定石の数から最終スコアの上限を求め、上限が`alpha`以下であれば(fail-low)、その上限を返す。
+///
+/// 相手の確定石の数を`n`とすると、最終スコアは`64 - 2 * n`を超えない。
+#[inline(always)]
+fn stability_cut_off(board: &Board, alpha: i32) -> Option<i32>
+{
+    // 相手の石が全て確定石だとしても上限が`alpha`を超える場合は、確定石を求めない
+    let n_opponent = board.bit_board[board.next_turn ^ 1].count_ones() as i32;
+    if 64 - 2 * n_opponent > alpha {
+        return None;
+    }
+
+    let max_score = 64 - 2 * board.opponent_stable_discs().count_ones() as i32;
+    if max_score <= alpha {
+        Some(max_score)
+    } else {
+        None
+    }
+}
+
 /// NegaAlpha法を用いて、完全読みを行い、オセロの盤面のスコアを計算する。
 ///
 /// 探索速度を向上させるため、葉に近いノードで使用される。
@@ -126,14 +307,12 @@ pub fn negaalpha_perfect(board: &Board, mut alpha: i32, beta: i32, search: &mut
     #[cfg(debug_assertions)]
     assert!(alpha <= beta);
 
-    // 空きマスがない場合
-    if (board.bit_board[Board::BLACK] | board.bit_board[Board::WHITE]) == u64::MAX {
-        search.perfect_search_node_count += 1;
-        search.perfect_search_leaf_node_count += 1;
-        return  solve_score_0_empties(board);
+    // 残り数マスは、専用の関数で探索する
+    if board.empties_count() <= search.config.switch_empties_last_moves {
+        return solve_last_moves(board, alpha, beta, search);
     }
     
-    let mut legal_moves = board.put_able();
+    let legal_moves = board.put_able();
 
     // 合法手がない
     if legal_moves == 0 {
@@ -144,7 +323,7 @@ pub fn negaalpha_perfect(board: &Board, mut alpha: i32, beta: i32, search: &mut
         }
         let passed_board = {
             let mut b = board.clone();
-            b.next_turn ^= 1;
+            b.pass();
             b
         };
         return -negaalpha_perfect(&passed_board, -beta, -alpha, search);
@@ -159,17 +338,21 @@ pub fn negaalpha_perfect(board: &Board, mut alpha: i32, beta: i32, search: &mut
     search.perfect_search_node_count += 1;
     let mut best_score: i32 = -SCORE_INF;
 
-    while legal_moves != 0 {
-        let mut current_board = board.clone();
-        let put_place: u64 = (!legal_moves + 1) & legal_moves;
-        legal_moves &= legal_moves - 1; // bitを削除
-        current_board.put_piece_fast(put_place);
-        let score: i32 = -negaalpha_perfect(&current_board, -beta, -alpha, search);
-        if score >= beta {
-            return score;
+    // 偶数理論: 空きマスが奇数個の領域の手を先に調べる
+    let odd_region = odd_parity_region(!(board.bit_board[Board::BLACK] | board.bit_board[Board::WHITE]));
+    for mut moves in [legal_moves & odd_region, legal_moves & !odd_region] {
+        while moves != 0 {
+            let mut current_board = board.clone();
+            let put_place: u64 = (!moves + 1) & moves;
+            moves &= moves - 1; // bitを削除
+            current_board.put_piece_fast(put_place);
+            let score: i32 = -negaalpha_perfect(&current_board, -beta, -alpha, search);
+            if score >= beta {
+                return score;
+            }
+            if score > alpha {alpha = score;}
+            if score > best_score {best_score = score}
         }
-        if score > alpha {alpha = score;}
-        if score > best_score {best_score = score}
     }
 
     best_score
@@ -195,7 +378,7 @@ pub fn nws_perfect_simple(board: &Board, mut alpha: i32, search: &mut Search) ->
     // 探索範囲: [alpha, beta]
     let beta: i32 = alpha + 1;
     
-    if board.empties_count() < SWITCH_EMPTIES_NEGA_ALPHA  {
+    if board.empties_count() < search.config.switch_empties_nega_alpha  {
         return negaalpha_perfect(board, alpha, beta, search);
     }
 
@@ -203,26 +386,31 @@ pub fn nws_perfect_simple(board: &Board, mut alpha: i32, search: &mut Search) ->
 
     if legal_moves == 0 {
         let mut board: Board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても置くところがない == ゲーム終了
             search.perfect_search_leaf_node_count += 1;
-            board.next_turn ^= 1;
+            board.pass();
             return  solve_score(&board);
         }
         return -nws_perfect_simple(&board, -beta, search);
     }
 
+    if let Some(score) = stability_cut_off(board, alpha) {
+        return score;
+    }
+
     match perfect_search_mpc(board, alpha, beta, search) {
         ProbCutResult::Cut(score) => {return score},
         ProbCutResult::FAIL => ()
     }
 
     // move ordering
-    let put_boards: Vec<PutBoard> = move_ordering_ffs(board, legal_moves, search);
+    let n_empties: i32 = board.empties_count();
+    let n_moves: usize = move_ordering_ffs(board, legal_moves, search);
 
     let mut best_score: i32 = -SCORE_INF;
-    for current_put_board in put_boards.iter() {
-        let current_put_board = &current_put_board.board;
+    for i in 0..n_moves {
+        let current_put_board = &search.nth_move(n_empties, i).board;
         let score: i32 = -nws_perfect_simple(current_put_board, -beta, search);
         if score >= beta {
             return score;
@@ -258,7 +446,7 @@ pub fn pvs_perfect_simple(board: &Board, alpha: i32,beta: i32, search: &mut Sear
     #[cfg(debug_assertions)]
     assert!(alpha <= beta);
 
-    if board.empties_count() < SWITCH_EMPTIES_NEGA_ALPHA  {
+    if board.empties_count() < search.config.switch_empties_nega_alpha  {
         return negaalpha_perfect(board, alpha, beta, search);
     }
 
@@ -269,9 +457,9 @@ pub fn pvs_perfect_simple(board: &Board, alpha: i32,beta: i32, search: &mut Sear
 
     if legal_moves == 0 {
         let mut board: Board = board.clone();
-        board.next_turn ^= 1; //pass
+        board.pass();
         if board.put_able() == 0 { // passしても置くところがない == ゲーム終了
-            board.next_turn ^= 1;
+            board.pass();
             search.perfect_search_leaf_node_count += 1;
             return  solve_score(&board);
         }
@@ -284,14 +472,14 @@ pub fn pvs_perfect_simple(board: &Board, alpha: i32,beta: i32, search: &mut Sear
     }
 
     // move ordering
-    let mut put_boards: Vec<PutBoard> = move_ordering_ffs(board, legal_moves, search);
-    let mut put_boards_iter = put_boards.iter_mut();
+    let n_empties: i32 = board.empties_count();
+    let n_moves: usize = move_ordering_ffs(board, legal_moves, search);
     
     let mut this_node_alpha: i32 = alpha;
     let mut best_score: i32;
     
     // first move
-    let first_child_board: &mut PutBoard = put_boards_iter.next().unwrap();
+    let first_child_board: PutBoard = search.nth_move(n_empties, 0);
     best_score =  -pvs_perfect_simple(&first_child_board.board, -beta, -this_node_alpha, search);
     if best_score >= beta { 
         return best_score;
@@ -299,8 +487,8 @@ pub fn pvs_perfect_simple(board: &Board, alpha: i32,beta: i32, search: &mut Sear
     if best_score > this_node_alpha {this_node_alpha = best_score;}
 
     // other move
-    for current_put_board in put_boards_iter {
-        let current_put_board: &Board = &current_put_board.board;
+    for i in 1..n_moves {
+        let current_put_board: &Board = &search.nth_move(n_empties, i).board;
         let mut score: i32 = -nws_perfect_simple(current_put_board, -this_node_alpha - 1, search);
         if score >= beta {
             return score;
@@ -339,10 +527,15 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
     let mut beta = alpha + 1;
 
     let n_empties: i32 = board.empties_count();
-    if n_empties < SWITCH_EMPTIES_SIMPLE_NWS  {
+    if n_empties < search.config.switch_empties_simple_nws  {
         return nws_perfect_simple(board, alpha, search);
     }
 
+    // ノード数の上限に達した場合は、探索を打ち切る (結果は使用されない)
+    if search.node_limit_exceeded() {
+        return alpha;
+    }
+
     search.perfect_search_node_count += 1;
 
     // 探索範囲: [alpha, beta]
@@ -357,12 +550,16 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
         // 合法手がある -> 探索を続ける
         let passed_board: Board = {
             let mut b: Board = board.clone();
-            b.next_turn ^= 1;
+            b.pass();
             b
         };
         return -nws_perfect(&passed_board, -beta, search);
     }
 
+    if let Some(score) = stability_cut_off(board, alpha) {
+        return score;
+    }
+
     if let Some(score) = t_table_cut_off(board, &mut alpha, &mut beta,60, search.selectivity_lv, search.t_table) {
         return score;
     }
@@ -371,10 +568,19 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
         ProbCutResult::Cut(score) => {return score},
         ProbCutResult::FAIL => ()
     }
+
+    // Enhanced Transposition Cutoff
+    if n_empties >= search.config.etc_min_empties {
+        if let Some((score, put_place)) = etc_cut_off(board, legal_moves, beta, 60, search.selectivity_lv, search.t_table) {
+            search.t_table_add(board, score, SCORE_INF, 60, search.selectivity_lv, put_place);
+            return score;
+        }
+    }
+
     // move ordering
-    let put_boards: Vec<PutBoard> = {
-        if n_empties > SWITCH_EMPTIES_MOVE_ORDER {
-            let mo_lv = (8 - 2 * (search.origin_board.empties_count() - n_empties)).max(MOVE_ORDERING_EVAL_LEVEL_T[n_empties as usize]);
+    let n_moves: usize = {
+        if n_empties > search.config.switch_empties_move_order {
+            let mo_lv = (8 - 2 * (search.origin_board.empties_count() - n_empties)).max(search.config.move_ordering_eval_level_t[n_empties as usize]);
             move_ordering_eval(board, legal_moves, mo_lv, search)
         } else {
             move_ordering_ffs(board, legal_moves, search)
@@ -384,10 +590,12 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
     let mut best_move: u8 = NO_COORD;
     let mut this_node_alpha: i32 = alpha;
     let mut best_score: i32 = -SCORE_INF;
-    for put in put_boards.iter() {
+    for i in 0..n_moves {
+        let put: &PutBoard = &search.nth_move(n_empties, i);
         let score: i32 = -nws_perfect(&put.board, -beta, search);
         if score >= beta {
-            search.t_table.add(board, score, SCORE_INF, 60, search.selectivity_lv, put.put_place);
+            search.t_table_add(board, score, SCORE_INF, 60, search.selectivity_lv, put.put_place);
+            search.move_history.update(board, put.put_place, n_empties);
             return score;
         }
         if score > this_node_alpha {this_node_alpha = score;}
@@ -398,9 +606,9 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
     }
 
     if best_score > alpha {
-        search.t_table.add(board, best_score, best_score, 60, search.selectivity_lv,  best_move);
+        search.t_table_add(board, best_score, best_score, 60, search.selectivity_lv,  best_move);
     } else {
-        search.t_table.add(board, -SCORE_INF, best_score, 60,search.selectivity_lv, best_move);
+        search.t_table_add(board, -SCORE_INF, best_score, 60,search.selectivity_lv, best_move);
     }
 
     best_score
@@ -422,9 +630,10 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
 ///   スコアは現在のプレイヤーから見た盤面の評価値を表す。
 ///
 /// # 例
-/// ```
+/// (`Search`はクレートの外に公開されていないため、doctestとしては実行しない)
+/// ```ignore
 /// let board = Board::new(); // オセロの初期盤面を生成
-/// let mut search = Search::new();
+/// let mut search = Search::new(&board, NO_MPC, &mut t_table, &mut evaluator);
 /// let alpha = -SCORE_INF; // 初期アルファ値の設定
 /// let beta = SCORE_INF; // 初期ベータ値の設定
 /// let score = pvs_perfect(&board, alpha, beta, &mut search);
@@ -437,10 +646,15 @@ pub fn nws_perfect(board: &Board, mut alpha: i32, search: &mut Search) -> i32
 pub fn pvs_perfect(board: &Board, mut alpha: i32,mut beta: i32, search: &mut Search) -> i32
 {
     let n_empties = board.empties_count();
-    if n_empties < SWITCH_EMPTIES_SIMPLE_PVS  {
+    if n_empties < search.config.switch_empties_simple_pvs  {
         return pvs_perfect_simple(board, alpha, beta, search);
     }
 
+    // ノード数の上限に達した場合は、探索を打ち切る (結果は使用されない)
+    if search.node_limit_exceeded() {
+        return alpha;
+    }
+
     #[cfg(debug_assertions)]
     assert!(alpha <= beta);
 
@@ -459,7 +673,7 @@ pub fn pvs_perfect(board: &Board, mut alpha: i32,mut beta: i32, search: &mut Sea
         // 合法手がある -> 探索を続ける
         let passed_board: Board = {
             let mut b: Board = board.clone();
-            b.next_turn ^= 1;
+            b.pass();
             b
         };
         return -pvs_perfect(&passed_board, -beta, -alpha, search);
@@ -476,41 +690,43 @@ pub fn pvs_perfect(board: &Board, mut alpha: i32,mut beta: i32, search: &mut Sea
     }
 
     // move ordering
-    let put_boards: Vec<PutBoard> = {
-        if n_empties > SWITCH_EMPTIES_MOVE_ORDER {
-            let mo_lv = (8 - 2 * (search.origin_board.empties_count() - n_empties)).max(MOVE_ORDERING_EVAL_LEVEL_T[n_empties as usize]);
+    let n_moves: usize = {
+        if n_empties > search.config.switch_empties_move_order {
+            let mo_lv = (8 - 2 * (search.origin_board.empties_count() - n_empties)).max(search.config.move_ordering_eval_level_t[n_empties as usize]);
             move_ordering_eval(board, legal_moves, mo_lv, search)
         } else {
             move_ordering_ffs(board, legal_moves, search)
         }
     };
 
-    let mut put_boards_iter: std::slice::Iter<'_, PutBoard> = put_boards.iter();
-    
     let mut this_node_alpha: i32 = alpha;
 
     // first move
-    let first_child_board: &PutBoard = put_boards_iter.next().unwrap();
+    let first_child_board: PutBoard = search.nth_move(n_empties, 0);
     let mut best_move: u8 = first_child_board.put_place;
     let mut best_score: i32 =  -pvs_perfect(&first_child_board.board, -beta, -this_node_alpha, search);
     if best_score >= beta { 
-        search.t_table.add(board, best_score, SCORE_INF, 60, search.selectivity_lv, best_move);
+        search.t_table_add(board, best_score, SCORE_INF, 60, search.selectivity_lv, best_move);
+        search.move_history.update(board, best_move, n_empties);
         return best_score;
     }
     this_node_alpha = this_node_alpha.max(best_score);
 
     // other move
-    for put in put_boards_iter {
+    for i in 1..n_moves {
+        let put: &PutBoard = &search.nth_move(n_empties, i);
         let mut score: i32 = -nws_perfect(&put.board, -this_node_alpha - 1, search);
         if score >= beta {
-            search.t_table.add(board, score, SCORE_INF, 60, search.selectivity_lv, first_child_board.put_place);
+            search.t_table_add(board, score, SCORE_INF, 60, search.selectivity_lv, first_child_board.put_place);
+            search.move_history.update(board, put.put_place, n_empties);
             return score;
         }
         if score > best_score {
             // 再探索
             score = -pvs_perfect(&put.board, -beta, -this_node_alpha, search);
             if score >= beta { 
-                search.t_table.add(board, score, SCORE_INF, 60, search.selectivity_lv, best_move);
+                search.t_table_add(board, score, SCORE_INF, 60, search.selectivity_lv, best_move);
+                search.move_history.update(board, put.put_place, n_empties);
                 return score;
             }
             if score > best_score {
@@ -523,9 +739,9 @@ pub fn pvs_perfect(board: &Board, mut alpha: i32,mut beta: i32, search: &mut Sea
     }
 
     if best_score > alpha { // alpha < best_score < beta
-        search.t_table.add(board, best_score, best_score, 60, search.selectivity_lv, best_move);
+        search.t_table_add(board, best_score, best_score, 60, search.selectivity_lv, best_move);
     } else { // best_score <= alpha
-        search.t_table.add(board, -SCORE_INF, best_score, 60, search.selectivity_lv, best_move);
+        search.t_table_add(board, -SCORE_INF, best_score, 60, search.selectivity_lv, best_move);
     }
 
     best_score
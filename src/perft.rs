@@ -0,0 +1,149 @@
+use std::time;
+
+use crate::board::*;
+
+/// 初期盤面からの`perft`の既知の値 (`PERFT_INITIAL_COUNTS[depth]`)
+///
+/// パスは1手として数え、終局した局面は葉として数える。
+pub const PERFT_INITIAL_COUNTS: [u64; 15] = [
+    1,
+    4,
+    12,
+    56,
+    244,
+    1396,
+    8200,
+    55092,
+    390216,
+    3005288,
+    24571284,
+    212258800,
+    1939886636,
+    18429641748,
+    184042084512,
+];
+
+/// 盤面から`depth`手先までの、全ての着手の組み合わせを数える。(葉ノードの数)
+///
+/// 合法手生成(`put_able`)と着手(`put_piece_fast`)の正しさの検証と、速度の計測に使用する。
+///
+/// # 引数
+/// * `board` - 数え始める盤面
+/// * `depth` - 何手先まで数えるか
+///
+/// # 戻り値
+/// * `depth`手先の葉ノードの数
+///
+/// # 注記
+/// * 合法手がなく、相手に合法手がある場合は、パスを1手として数える。
+/// * `depth`手に達する前に終局した場合は、その局面を葉として数える。
+pub fn perft(board: &Board, depth: i32) -> u64
+{
+    if depth <= 0 {
+        return 1;
+    }
+
+    let mut legal_moves = board.put_able();
+
+    if legal_moves == 0 {
+        if board.opponent_put_able() == 0 { // 終局
+            return 1;
+        }
+        if depth == 1 {
+            return 1;
+        }
+        let mut passed_board = board.clone();
+        passed_board.pass();
+        return perft(&passed_board, depth - 1);
+    }
+
+    if depth == 1 {
+        return legal_moves.count_ones() as u64;
+    }
+
+    let mut count = 0;
+    while legal_moves != 0 {
+        let put_place = (!legal_moves + 1) & legal_moves;
+        legal_moves &= legal_moves - 1;
+        let mut current_board = board.clone();
+        current_board.put_piece_fast(put_place);
+        count += perft(&current_board, depth - 1);
+    }
+
+    count
+}
+
+/// 初期盤面から`max_depth`手先までの`perft`を実行し、既知の値と照合した結果と速度を出力する。
+///
+/// # 戻り値
+/// * 全ての深さで既知の値と一致した場合(既知の値がない深さは除く)、`true`
+pub fn perft_test(max_depth: i32) -> bool
+{
+    let board = Board::new();
+    let mut all_ok = true;
+
+    for depth in 1..=max_depth {
+        let now = time::Instant::now();
+        let count = perft(&board, depth);
+        let end = now.elapsed();
+
+        let result = match PERFT_INITIAL_COUNTS.get(depth as usize) {
+            Some(&expected) if expected == count => "OK".to_string(),
+            Some(&expected) => {
+                all_ok = false;
+                format!("NG (expected: {})", expected)
+            },
+            None => "-".to_string()
+        };
+
+        println!("depth: {:>2}, count: {:>14}, time: {:>12?}, nps [/s]: {:>14.0}, {}",
+            depth, count, end, count as f64 / end.as_secs_f64(), result);
+    }
+
+    all_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perft_initial_position() {
+        for depth in 0..=10 {
+            assert_eq!(perft(&Board::new(), depth), PERFT_INITIAL_COUNTS[depth as usize], "depth: {}", depth);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_initial_position_deep() {
+        for depth in 11..=12 {
+            assert_eq!(perft(&Board::new(), depth), PERFT_INITIAL_COUNTS[depth as usize], "depth: {}", depth);
+        }
+    }
+
+    #[test]
+    fn perft_symmetric_positions() {
+        // 対称な盤面では、perftの値は等しい
+        let mut board = Board::new();
+        assert!(board.put_piece(1 << F5).is_ok());
+        assert!(board.put_piece(1 << D6).is_ok());
+        let expected = perft(&board, 6);
+        for transform in 1..Board::N_SYMMETRIES {
+            assert_eq!(perft(&board.transformed(transform), 6), expected);
+        }
+    }
+
+    #[test]
+    fn perft_pass() {
+        // 黒: a1, 白: b1 のみ。黒は c1 に置けるが、白は置けない。
+        let board = Board::from_bit_board([1 << A1, 1 << B1], Board::WHITE);
+        assert_eq!(board.put_able(), 0);
+        assert_eq!(board.opponent_put_able(), 1 << C1);
+        assert_eq!(perft(&board, 1), 1);
+        assert_eq!(perft(&board, 2), 1);
+        // 黒がc1に置くと、白の石がなくなり終局 (終局した局面は葉として数える)
+        assert_eq!(perft(&board, 3), 1);
+        assert_eq!(perft(&board, 10), 1);
+    }
+}
@@ -0,0 +1,245 @@
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+use crate::board::*;
+use crate::book::Book;
+use crate::solver::*;
+use crate::t_table::*;
+use crate::eval::*;
+
+/// 着手の選び方
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayStyle {
+    /// 常に最善手を選ぶ
+    Best,
+    /// 最善手との評価値の差が、指定した石数以内の手から一様に選ぶ
+    WithinMargin(i32),
+    /// 評価値に対するソフトマックス関数の確率で選ぶ (引数は温度)
+    ///
+    /// 温度が高いほどランダムになり、0以下の場合は常に最善手を選ぶ。
+    Softmax(f64),
+    /// 全ての合法手から一様に選ぶ
+    Random,
+}
+
+/// 対局時の着手の選び方の設定
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayConfig {
+    /// 序盤以外での着手の選び方
+    pub style: PlayStyle,
+    /// 序盤とみなす手数 (`Board::move_count() < opening_moves`の局面で`opening_style`を使用する)
+    pub opening_moves: i32,
+    /// 序盤での着手の選び方
+    pub opening_style: PlayStyle,
+}
+
+impl Default for PlayConfig {
+    fn default() -> Self {
+        Self {
+            style: PlayStyle::Best,
+            opening_moves: 0,
+            opening_style: PlayStyle::Best,
+        }
+    }
+}
+
+impl PlayConfig {
+    /// 盤面で使用する着手の選び方
+    pub fn style_for(&self, board: &Board) -> PlayStyle {
+        if board.move_count() < self.opening_moves {
+            self.opening_style
+        } else {
+            self.style
+        }
+    }
+}
+
+/// 評価値を元に、着手の選び方に従って手を選ぶ。
+///
+/// # 引数
+/// * `results` - 各合法手の評価値 (`eval_all_moves`の戻り値のように、評価値の高い順に並んでいること)
+///
+/// # 戻り値
+/// * 選ばれた手の`results`でのインデックス
+pub fn select_move<R: Rng>(results: &[SolverResult], style: PlayStyle, rng: &mut R) -> usize {
+    #[cfg(debug_assertions)]
+    assert!(results.windows(2).all(|w| w[0].eval >= w[1].eval));
+
+    if results.len() <= 1 {
+        return 0;
+    }
+    let best = results[0].eval;
+
+    match style {
+        PlayStyle::Best => 0,
+        PlayStyle::WithinMargin(margin) => {
+            let n = results.iter().take_while(|r| best - r.eval <= margin.max(0)).count();
+            rng.gen_range(0..n)
+        },
+        PlayStyle::Softmax(temperature) => {
+            if temperature <= 0.0 {
+                return 0;
+            }
+            let weights: Vec<f64> = results.iter()
+                .map(|r| ((r.eval - best) as f64 / temperature).exp())
+                .collect();
+            let mut x = rng.gen::<f64>() * weights.iter().sum::<f64>();
+            for (i, w) in weights.iter().enumerate() {
+                if x < *w {
+                    return i;
+                }
+                x -= w;
+            }
+            results.len() - 1
+        },
+        PlayStyle::Random => rng.gen_range(0..results.len()),
+    }
+}
+
+/// 着手の選び方に従って手を選ぶ対局者
+///
+/// 乱数のシードを指定することで、同じ対局を再現できる。
+pub struct Player {
+    pub config: PlayConfig,
+    rng: XorShiftRng,
+}
+
+impl Player {
+    pub fn new(config: PlayConfig, seed: u64) -> Self {
+        Self {
+            config,
+            rng: XorShiftRng::seed_from_u64(seed),
+        }
+    }
+
+    /// 盤面に対する手を選ぶ。
+    ///
+    /// `book`に盤面がある場合は、探索せずに定石の着手から選ぶ。
+    /// それ以外で、最善手を選ぶ場合は`eval_solver`を、それ以外の場合は`eval_all_moves`を使用する。
+    ///
+    /// # 戻り値
+    /// * 選ばれた手とその評価値。`node_count`と`leaf_node_count`は、全ての探索の合計。(定石の手の場合は0)
+    /// * 合法手が存在しない場合は、`SolverErr::NoMove`
+    pub fn choose_move(&mut self, board: &Board, lv: i32, selectivity_lv: i32, book: Option<&Book>, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> Result<SolverResult, SolverErr> {
+        let style = self.config.style_for(board);
+        let mut book_results: Vec<SolverResult> = book.and_then(|book| book.probe(board)).unwrap_or_default().iter()
+            .filter(|m| board.put_able() & (1 << m.put_place) != 0)
+            .map(|m| SolverResult { best_move: 1 << m.put_place, eval: m.value as i32, node_count: 0, leaf_node_count: 0 })
+            .collect();
+        if !book_results.is_empty() {
+            let index = select_move(&book_results, style, &mut self.rng);
+            return Ok(book_results.swap_remove(index));
+        }
+
+        if style == PlayStyle::Best {
+            return eval_solver(board, lv, selectivity_lv, false, t_table, evaluator);
+        }
+
+        let results = eval_all_moves(board, lv, selectivity_lv, t_table, evaluator)?;
+        let index = select_move(&results, style, &mut self.rng);
+        Ok(SolverResult {
+            best_move: results[index].best_move,
+            eval: results[index].eval,
+            node_count: results.iter().map(|r| r.node_count).sum(),
+            leaf_node_count: results.iter().map(|r| r.leaf_node_count).sum(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpc::NO_MPC;
+    use crate::book::parse_transcript;
+
+    fn results(evals: &[i32]) -> Vec<SolverResult> {
+        evals.iter().enumerate()
+            .map(|(i, &eval)| SolverResult { best_move: 1 << i, eval, node_count: 0, leaf_node_count: 0 })
+            .collect()
+    }
+
+    #[test]
+    fn select_move_styles() {
+        let results = results(&[10, 8, 7, 2, -20]);
+        let mut rng = XorShiftRng::seed_from_u64(0);
+
+        assert_eq!(select_move(&results, PlayStyle::Best, &mut rng), 0);
+        assert_eq!(select_move(&results, PlayStyle::Softmax(0.0), &mut rng), 0);
+
+        let mut counts = [0; 5];
+        for _ in 0..1000 {
+            counts[select_move(&results, PlayStyle::WithinMargin(3), &mut rng)] += 1;
+        }
+        assert!(counts[..3].iter().all(|&c| c > 0));
+        assert_eq!(counts[3] + counts[4], 0);
+
+        // 温度が低いほど、最善手が選ばれやすい
+        let mut count_best = |temperature| (0..1000)
+            .filter(|_| select_move(&results, PlayStyle::Softmax(temperature), &mut rng) == 0)
+            .count();
+        let (low, high) = (count_best(0.5), count_best(10.0));
+        assert!(low > high);
+        assert!(low > 900);
+
+        let mut counts = [0; 5];
+        for _ in 0..1000 {
+            counts[select_move(&results, PlayStyle::Random, &mut rng)] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 0));
+    }
+
+    #[test]
+    fn player_is_reproducible() {
+        let config = PlayConfig {
+            style: PlayStyle::Softmax(2.0),
+            opening_moves: 4,
+            opening_style: PlayStyle::Random,
+        };
+        let play_game = |seed| {
+            let mut player = Player::new(config, seed);
+            let mut t_table = TranspositionTable::new();
+            let mut evaluator = Evaluator::new();
+            let mut board = Board::new();
+            let mut moves = Vec::new();
+            for _ in 0..8 {
+                let result = player.choose_move(&board, 2, NO_MPC, None, &mut t_table, &mut evaluator).ok().unwrap();
+                assert_ne!(board.put_able() & result.best_move, 0);
+                moves.push(result.best_move);
+                board.put_piece_fast(result.best_move);
+                if board.put_able() == 0 {
+                    board.pass();
+                }
+            }
+            moves
+        };
+        assert_eq!(play_game(1), play_game(1));
+        assert!((2..10).any(|seed| play_game(seed) != play_game(1)));
+    }
+
+    #[test]
+    fn player_chooses_book_moves_without_searching() {
+        let mut book = Book::new();
+        assert!(book.add_line(&parse_transcript("f5d6").unwrap(), 60));
+        assert!(book.add_line(&parse_transcript("f5f6").unwrap(), 60));
+        let mut board = Board::new();
+        board.put_piece_fast(1 << F5);
+        let book_moves = (1 << D6) | (1 << F6);
+
+        let mut player = Player::new(PlayConfig { style: PlayStyle::Random, ..PlayConfig::default() }, 0);
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+        let mut chosen = 0;
+        for _ in 0..100 {
+            let result = player.choose_move(&board, 2, NO_MPC, Some(&book), &mut t_table, &mut evaluator).ok().unwrap();
+            assert_eq!(result.node_count, 0);
+            chosen |= result.best_move;
+        }
+        // 定石の手のみから選ばれる
+        assert_eq!(chosen, book_moves);
+
+        // 定石にない盤面は探索する
+        board.put_piece_fast(1 << D6);
+        let result = player.choose_move(&board, 2, NO_MPC, Some(&book), &mut t_table, &mut evaluator).ok().unwrap();
+        assert!(result.node_count > 0);
+    }
+}
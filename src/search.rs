@@ -2,21 +2,102 @@ use crate::board::*;
 use crate::eval::Evaluator;
 use crate::eval_search::*;
 use crate::t_table::*;
+use crate::search_observer::*;
+use crate::move_history::*;
+use crate::search_config::*;
+use crate::mpc::{SELECTIVITY, SELECTIVITY_LV_MAX};
+use std::time;
 
 const SCORE_INF: i32 = i8::MAX as i32;
 
 
 /// 
+#[derive(Clone)]
 pub struct PutBoard {
     eval: i32,
     pub board: Board,
     pub put_place: u8
 }
 
+/// 合法手の数の最大値
+pub const MAX_MOVES: usize = 33;
+
+/// 着手後の盤面(`PutBoard`)の固定長のリスト
+///
+/// `MAX_MOVES`個分の領域を持ち、要素の追加でメモリの確保を行わない。
+/// 探索中は、`Search`が空きマス数ごとに持つリストを使い回すため、内部ノードでメモリの確保を行わない。(`Search::move_list`)
+/// (`Vec<PutBoard>`では、内部ノードごとに1回の確保が必要だった。探索速度の差は、計測の誤差の範囲内)
+/// `Deref`により、`&[PutBoard]`として扱うことができる。
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [PutBoard; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    const EMPTY: PutBoard = PutBoard {
+        eval: 0,
+        board: Board::EMPTY,
+        put_place: NO_COORD
+    };
+
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            moves: [Self::EMPTY; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn push(&mut self, put_board: PutBoard) {
+        self.moves[self.len] = put_board;
+        self.len += 1;
+    }
+
+    #[inline(always)]
+    fn clear(&mut self) {
+        self.len = 0;
+    }
+}
+
+impl Default for MoveList {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for MoveList {
+    type Target = [PutBoard];
+
+    #[inline(always)]
+    fn deref(&self) -> &[PutBoard] {
+        &self.moves[..self.len]
+    }
+}
+
+impl std::ops::DerefMut for MoveList {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [PutBoard] {
+        &mut self.moves[..self.len]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a PutBoard;
+    type IntoIter = std::slice::Iter<'a, PutBoard>;
+
+    #[inline(always)]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 /// 評価関数に基づいて、手の順序を決定するための関数。
 ///
 /// この関数は、オセロの盤面上で着手可能な各手に対して、評価値の基づく探索を実施し、
 /// それぞれの手の評価値に基づいて手を並び替える。
+/// 置換表の最善手を最優先とし、評価値が同じ手は、キラー手とヒストリー(`MoveHistory`)による加点の順に並べる。
 ///
 /// # 引数
 /// * `board` - 現在のオセロの盤面を表す `Board` オブジェクトの参照。
@@ -24,7 +105,8 @@ pub struct PutBoard {
 /// * `lv` - 探索の深さを表す整数値。
 ///
 /// # 戻り値
-/// * `PutBoard` オブジェクトのベクター。
+/// * 合法手の数。
+///   並び替えた手(`PutBoard`)のリストは、`search.move_list(board.empties_count())`に格納される。
 ///   * 各手の評価値(`i32`), 
 ///   * 着手された盤面(`Board`), 
 ///   * ビットボードで表された着手した箇所(`u64`)
@@ -33,15 +115,17 @@ pub struct PutBoard {
 /// * `move_ordering_ffs`との違い
 ///   * `move_ordering_eval`は、評価値の高い順に並び替える。
 ///   * `move_ordering_ffs`は、相手の合法手が少ない順に並び替える。
-pub fn move_ordering_eval(board: &Board, mut legal_moves: u64, lv: i32, search: &mut Search) -> Vec<PutBoard>
+pub fn move_ordering_eval(board: &Board, mut legal_moves: u64, lv: i32, search: &mut Search) -> usize
 {
-    let mut put_boards: Vec<PutBoard> = Vec::with_capacity(legal_moves.count_ones() as usize);
+    let n_empties = board.empties_count();
+    search.move_lists[n_empties as usize].clear();
     let tt_best_move =
         if let Some(t) = search.t_table.get(board) {
             1u64 << t.best_move
         } else {
             0u64
         };
+    let history_bonus = search.move_history.bonus(board, legal_moves);
     
     while legal_moves != 0 {
         let put_place = (!legal_moves + 1) & legal_moves;
@@ -50,38 +134,85 @@ pub fn move_ordering_eval(board: &Board, mut legal_moves: u64, lv: i32, search:
         put_board.put_piece_fast(put_place);
         let eval = 
         if tt_best_move == put_place {
-            SCORE_INF
+            SCORE_INF * MOVE_HISTORY_SCALE
         } else {
             let main_search_selectivity_lv = search.selectivity_lv;
             let e = -pvs_eval(&put_board, -SCORE_INF, SCORE_INF, lv-1, search);
             search.selectivity_lv = main_search_selectivity_lv;
-            e
+            e * MOVE_HISTORY_SCALE + history_bonus[put_place.trailing_zeros() as usize]
         };
-        put_boards.push(PutBoard{eval: eval, board: put_board, put_place: put_place.trailing_zeros() as u8});
+        // 子ノードの探索は、空きマス数がより少ないリストのみを使用する
+        search.move_lists[n_empties as usize].push(PutBoard{eval, board: put_board, put_place: put_place.trailing_zeros() as u8});
     }
 
+    let put_boards = &mut search.move_lists[n_empties as usize];
     if put_boards.len() > 2 {
         put_boards.sort_unstable_by(|a, b| b.eval.partial_cmp(&a.eval).unwrap());
     }
 
-    put_boards
+    put_boards.len()
 }
 
 
 
 
+/// 盤面を4分割した領域のマスク
+const QUADRANT_MASKS: [u64; 4] = [
+    0x000000000f0f0f0f,
+    0x00000000f0f0f0f0,
+    0x0f0f0f0f00000000,
+    0xf0f0f0f000000000,
+];
+
+const CORNERS: u64 = 0x8100000000000081;
+
+/// 空きマス`empties`が奇数個ある領域(盤面を4分割した領域)のマスクを求める。
+///
+/// 終盤では、空きマスが奇数個の領域に先に打つ手が有力である。(偶数理論)
+#[inline(always)]
+pub fn odd_parity_region(empties: u64) -> u64 {
+    let mut region = 0;
+    for mask in QUADRANT_MASKS {
+        if (empties & mask).count_ones() % 2 == 1 {
+            region |= mask;
+        }
+    }
+    region
+}
+
+/// 隣接する隅が空きマスであるX打ち(b2, g2, b7, g7)のマスクを求める。
+#[inline(always)]
+fn x_squares_next_to_empty_corners(empties: u64) -> u64 {
+    let corners = empties & CORNERS;
+    ((corners & (1 << A1)) << 9) | ((corners & (1 << H1)) << 7) | ((corners & (1 << A8)) >> 7) | ((corners & (1 << H8)) >> 9)
+}
+
+/// `move_ordering_ffs`における、相手の合法手1つあたりの減点
+const FFS_MOBILITY_WEIGHT: i32 = 4;
+/// `move_ordering_ffs`における、空きマスが奇数個の領域の手の加点
+const FFS_PARITY_BONUS: i32 = 2;
+/// `move_ordering_ffs`における、隅の手の加点
+const FFS_CORNER_BONUS: i32 = 2;
+/// `move_ordering_ffs`における、隣接する隅が空きマスであるX打ちの減点
+const FFS_X_SQUARE_PENALTY: i32 = 4;
+
 /// 速さ優先探索(Fast First Search)のための、move ordering
 /// 合法手を評価し、手の順序を決定するための関数。
 ///
 /// オセロの盤面上で着手可能な各手に対して、
 /// 合法手が少ない順に手を並び替える。
+/// 相手の合法手の数が同程度の手は、次の順に優先する。
+/// * 空きマスが奇数個の領域の手 (`odd_parity_region`)
+/// * 隅の手
+/// * 隣接する隅が空きマスであるX打ちは後回しにする
 ///
 /// # 引数
 /// * `board` - 現在のオセロの盤面を表す `Board` オブジェクトの参照。
 /// * `legal_moves` - 現在の手番のプレイヤーが打てる合法手を表すビットボード。
 ///
 /// # 戻り値
-/// * `PutBoard` オブジェクトのベクター。
+/// * 合法手の数。
+///   並び替えた手(`PutBoard`)のリストは、`search.move_list(board.empties_count())`に格納される。
 ///   * 各手の評価値(`i32`), 
 ///   * 着手された盤面(`Board`), 
 ///   * ビットボードで表された着手した箇所(`u64`)
@@ -91,9 +222,14 @@ pub fn move_ordering_eval(board: &Board, mut legal_moves: u64, lv: i32, search:
 ///   * `move_ordering_eval`は、評価値の高い順に並び替える。
 ///   * `move_ordering_ffs`は、相手の合法手が少ない順に並び替える。
 #[inline(always)]
-pub fn move_ordering_ffs(board: &Board, mut legal_moves: u64, _search: &mut Search) -> Vec<PutBoard>
+pub fn move_ordering_ffs(board: &Board, mut legal_moves: u64, search: &mut Search) -> usize
 {
-    let mut put_boards: Vec<PutBoard> = Vec::with_capacity(legal_moves.count_ones() as usize);
+    let put_boards = &mut search.move_lists[board.empties_count() as usize];
+    put_boards.clear();
+
+    let empties = !(board.bit_board[Board::BLACK] | board.bit_board[Board::WHITE]);
+    let odd_region = odd_parity_region(empties);
+    let x_squares = x_squares_next_to_empty_corners(empties);
 
     while legal_moves != 0 {
         let put_place = (!legal_moves + 1) & legal_moves;
@@ -101,20 +237,23 @@ pub fn move_ordering_ffs(board: &Board, mut legal_moves: u64, _search: &mut Sear
         let mut put_board = board.clone();
         put_board.put_piece_fast(put_place);
 
-        let eval = -(put_board.put_able().count_ones() as i32);
-        put_boards.push(PutBoard{eval: eval, board: put_board, put_place: put_place.trailing_zeros() as u8})
+        let mut eval = -(put_board.put_able().count_ones() as i32) * FFS_MOBILITY_WEIGHT;
+        if put_place & odd_region != 0 {eval += FFS_PARITY_BONUS;}
+        if put_place & CORNERS != 0 {eval += FFS_CORNER_BONUS;}
+        if put_place & x_squares != 0 {eval -= FFS_X_SQUARE_PENALTY;}
+        put_boards.push(PutBoard{eval, board: put_board, put_place: put_place.trailing_zeros() as u8})
     }
 
     if put_boards.len() > 2{
         put_boards.sort_unstable_by(|a, b| b.eval.partial_cmp(&a.eval).unwrap());
     }
-    put_boards
+    put_boards.len()
 }
 
 #[inline(always)]
-pub fn get_put_boards(board: &Board, mut legal_moves: u64) -> Vec<PutBoard>
+pub fn get_put_boards(board: &Board, mut legal_moves: u64) -> MoveList
 {
-    let mut put_boards: Vec<PutBoard> = Vec::with_capacity(legal_moves.count_ones() as usize);
+    let mut put_boards = MoveList::new();
 
     while legal_moves != 0 {
         let put_place = (!legal_moves + 1) & legal_moves;
@@ -149,6 +288,42 @@ pub fn t_table_cut_off(
     None
 }
 
+/// Enhanced Transposition Cutoff (ETC)
+///
+/// 子ノードを探索する前に、全ての子ノードの盤面を置換表から探し、
+/// いずれかの子ノードの上限値から、この盤面で`beta`以上となること(fail-high)が分かれば、その値を返す。
+///
+/// # 引数
+/// * `lv` - 子ノードの探索レベル (完全読みの場合は`60`)
+///
+/// # 戻り値
+/// * `(評価値, 着手箇所)` - fail-highが確定した場合
+/// * `None` - 確定しない場合
+#[inline(always)]
+pub fn etc_cut_off(
+    board   :       & Board,
+    mut legal_moves: u64,
+    beta    :       i32,
+    lv      :       i32,
+    selectivity_lv: i32,
+    t_table :       & TranspositionTable ) -> Option<(i32, u8)>
+{
+    while legal_moves != 0 {
+        let put_place = legal_moves & legal_moves.wrapping_neg();
+        legal_moves &= legal_moves - 1;
+        let mut put_board = board.clone();
+        put_board.put_piece_fast(put_place);
+        if let Some(t) = t_table.get(&put_board) {
+            if t.lv as i32 != lv || t.selectivity_lv as i32 != selectivity_lv {continue;}
+            let score = -(t.max as i32);
+            if score >= beta {
+                return Some((score, put_place.trailing_zeros() as u8));
+            }
+        }
+    }
+    None
+}
+
 pub struct Search<'a> {
     pub eval_search_node_count: u64,
     pub eval_search_leaf_node_count: u64,
@@ -158,6 +333,17 @@ pub struct Search<'a> {
     pub origin_board: Board,
     pub eval_func: &'a mut Evaluator,
     pub selectivity_lv: i32,
+    /// 探索の進行状況を受け取るオブザーバー
+    pub observer: Option<&'a mut dyn SearchObserver>,
+    pub start_time: time::Instant,
+    /// 探索ノード数の上限 (`node_count()`がこの値に達すると、探索を打ち切る)
+    pub node_limit: u64,
+    /// move orderingに用いるキラー手とヒストリー
+    pub move_history: MoveHistory,
+    /// 空きマス数ごとの、move orderingで並び替えた手のリスト
+    move_lists: Vec<MoveList>,
+    /// 探索の閾値などのパラメータ (`set_config`で変更する)
+    pub config: SearchConfig,
 }
 
 impl Search<'_> {
@@ -170,7 +356,118 @@ impl Search<'_> {
             t_table,
             origin_board: board.clone(),
             eval_func: evaluator,
-            selectivity_lv
+            selectivity_lv,
+            observer: None,
+            start_time: time::Instant::now(),
+            node_limit: u64::MAX,
+            move_history: MoveHistory::new(SearchConfig::default().use_move_history),
+            move_lists: vec![MoveList::new(); 61],
+            config: SearchConfig::default(),
         }
     }
-}
\ No newline at end of file
+
+    /// 探索のパラメータを`config`に変更する。
+    pub fn set_config(&mut self, config: &SearchConfig) {
+        self.move_history.enabled = config.use_move_history;
+        self.config = config.clone();
+    }
+
+    /// 空きマス数`n_empties`の局面で、`move_ordering_eval`や`move_ordering_ffs`が並び替えた手のリスト
+    ///
+    /// 子ノードの探索では、空きマス数がより少ないリストのみが上書きされるため、
+    /// 探索中のノードは、子ノードを探索しながら自身のリストを参照できる。
+    #[inline(always)]
+    pub fn move_list(&self, n_empties: i32) -> &MoveList {
+        &self.move_lists[n_empties as usize]
+    }
+
+    /// `move_list(n_empties)`の`i`番目の手
+    ///
+    /// 子ノードの探索は`&mut Search`を必要とするため、参照ではなく複製を返す。
+    /// (`PutBoard`は40バイトで、参照で走査した場合との速度の差は、計測の誤差の範囲内)
+    #[inline(always)]
+    pub fn nth_move(&self, n_empties: i32, i: usize) -> PutBoard {
+        self.move_lists[n_empties as usize][i].clone()
+    }
+
+    /// 探索したノード数 (評価関数による探索と完全読みの合計)
+    #[inline(always)]
+    pub fn node_count(&self) -> u64 {
+        self.eval_search_node_count + self.perfect_search_node_count
+    }
+
+    /// 探索ノード数が上限に達したか
+    ///
+    /// 上限に達した後の探索結果は正しくないため、置換表には保存されない。
+    #[inline(always)]
+    pub fn node_limit_exceeded(&self) -> bool {
+        self.node_count() >= self.node_limit
+    }
+
+    /// 置換表に探索結果を保存する。(探索ノード数が上限に達した後は保存しない)
+    #[inline(always)]
+    pub fn t_table_add(&mut self, board: &Board, min: i32, max: i32, lv: i32, selectivity_lv: i32, best_move: u8) {
+        if !self.node_limit_exceeded() {
+            self.t_table.add(board, min, max, lv, selectivity_lv, best_move);
+        }
+    }
+
+    /// オブザーバーにイベントを通知する。
+    pub fn notify(&mut self, event: &SearchEvent) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer.notify(event);
+        }
+    }
+
+    /// 探索の途中経過を求める。
+    ///
+    /// `best_move`が`None`の場合は、置換表に保存されたルートの最善手を使用する。
+    pub fn progress(&self, depth: i32, best_move: Option<u8>, score: i32) -> SearchProgress {
+        let best_move = best_move.or_else(|| {
+            self.t_table.get(&self.origin_board)
+                .map(|t| t.best_move)
+                .filter(|&m| m < 64 && self.origin_board.put_able() & (1 << m) != 0)
+        });
+        SearchProgress {
+            depth,
+            selectivity_lv: self.selectivity_lv,
+            confidence: SELECTIVITY[self.selectivity_lv.clamp(0, SELECTIVITY_LV_MAX) as usize].percent,
+            best_move,
+            score,
+            pv: best_move.map(|m| principal_variation(&self.origin_board, m, self.t_table, depth.max(1) as usize)).unwrap_or_default(),
+            node_count: self.node_count(),
+            elapsed: self.start_time.elapsed(),
+        }
+    }
+}
+
+/// 置換表に保存された最善手をたどり、最善応手手順を求める。
+///
+/// # 引数
+/// * `first_move` - ルートでの着手
+/// * `max_len` - 手順の最大の長さ
+///
+/// # 注記
+/// * パスは手順に含まない。
+/// * 置換表に盤面がない場合は、そこで手順が途切れる。
+pub fn principal_variation(board: &Board, first_move: u8, t_table: &TranspositionTable, max_len: usize) -> Vec<u8> {
+    let mut pv = vec![first_move];
+    let mut board = board.clone();
+    board.put_piece_fast(1 << first_move);
+
+    while pv.len() < max_len {
+        if board.put_able() == 0 {
+            if board.opponent_put_able() == 0 {
+                break;
+            }
+            board.pass();
+        }
+        let Some(t) = t_table.get(&board) else {break};
+        if t.best_move >= 64 || board.put_able() & (1 << t.best_move) == 0 {
+            break;
+        }
+        pv.push(t.best_move);
+        board.put_piece_fast(1 << t.best_move);
+    }
+    pv
+}
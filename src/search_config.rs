@@ -0,0 +1,230 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mpc::MpcTable;
+
+/// `SearchConfig::move_ordering_eval_level_t`の長さ (空きマス数 0 ~ 60)
+const N_EMPTIES_TABLE: usize = 61;
+
+/// `solve_last_moves`が扱える空きマス数の最大値
+const MAX_SWITCH_EMPTIES_LAST_MOVES: i32 = 4;
+
+/// 自動調整(`tune_tool`)の対象とする整数のパラメータ
+///
+/// `(パラメータ名, 調整する範囲の最小値, 最大値)`
+pub const TUNABLE_PARAMS: [(&str, i32, i32); 11] = [
+    ("switch_empties_simple_pvs",               6, 12),
+    ("switch_empties_simple_nws",               6, 12),
+    ("switch_empties_last_moves",               0, MAX_SWITCH_EMPTIES_LAST_MOVES),
+    ("switch_empties_nega_alpha",               4,  9),
+    ("switch_empties_move_order",              10, 20),
+    ("etc_min_empties",                         8, 20),
+    ("move_ordering_eval_level",                0,  3),
+    ("move_ordering_eval_level_simple_search",  0,  3),
+    ("switch_simple_search_level",              3,  9),
+    ("switch_negaalpha_search_level",           2,  6),
+    ("etc_min_level",                           4, 12),
+];
+
+/// 探索の閾値などのパラメータ
+///
+/// `Search`が持ち、`perfect_search.rs`, `eval_search.rs`, `mpc.rs`の探索関数はこの値を参照する。
+/// JSONファイルから読み込むことで、再コンパイルせずにパラメータを調整できる。
+/// ファイルに含まれない項目は、既定値(`SearchConfig::default()`)となる。
+/// 項目名の誤りに気付けるよう、未知の項目はエラーとする。
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig {
+    /// 空きマスが残り`switch_empties_simple_pvs`未満である場合、
+    /// `pvs_perfect`から、`pvs_perfect_simple`へ切り替える
+    pub switch_empties_simple_pvs: i32,
+    /// 空きマスが残り`switch_empties_simple_nws`未満である場合、
+    /// `nws_perfect`から、`nws_perfect_simple`へ切り替える
+    pub switch_empties_simple_nws: i32,
+    /// 空きマスが残り`switch_empties_last_moves`以下である場合、
+    /// `negaalpha_perfect`から、残りマス数ごとの専用の関数(`solve_last_moves`)へ切り替える (4以下)
+    pub switch_empties_last_moves: i32,
+    /// 空きマスが残り`switch_empties_nega_alpha`未満である場合、
+    /// `pvs_perfect_simple`や`nws_perfect_simple`から、`negaalpha_perfect`へ切り替える
+    pub switch_empties_nega_alpha: i32,
+    /// `pvs_perfect`, `nws_perfect`でのmove orderingにおいて、
+    /// 空きマスが`switch_empties_move_order`より多い場合は`move_ordering_eval`を、
+    /// それ以下である場合は`move_ordering_ffs`を使用する。
+    pub switch_empties_move_order: i32,
+    /// `nws_perfect`において、空きマスが`etc_min_empties`以上である場合、Enhanced Transposition Cutoffを行う。
+    pub etc_min_empties: i32,
+    /// `pvs_perfect`, `nws_perfect`でのmove orderingにおいて、`move_ordering_eval`を使用する場合の、
+    /// 空きマス数ごとの探索の深さ
+    pub move_ordering_eval_level_t: Vec<i32>,
+
+    /// `pvs_eval`, `nws_eval`のmove orderingにおける探索の深さ
+    pub move_ordering_eval_level: i32,
+    /// `pvs_eval_simple`のmove orderingにおける探索の深さ
+    pub move_ordering_eval_level_simple_search: i32,
+    /// 探索レベルが`switch_simple_search_level`未満である場合、
+    /// `pvs_eval`, `nws_eval`から、置換表を使用しない`pvs_eval_simple`, `nws_eval_simple`へ切り替える
+    pub switch_simple_search_level: i32,
+    /// 探索レベルが`switch_negaalpha_search_level`未満である場合、
+    /// `pvs_eval_simple`, `nws_eval_simple`から、`negaalpha_eval`へ切り替える
+    pub switch_negaalpha_search_level: i32,
+    /// `nws_eval`において、探索レベルが`etc_min_level`以上である場合、Enhanced Transposition Cutoffを行う。
+    pub etc_min_level: i32,
+
+    /// 評価関数による探索で、探索レベルが`mpc_start_level_eval_search`以上である場合、MPCを行う。
+    pub mpc_start_level_eval_search: i32,
+    /// MPCのパラメータのファイル (`MpcTable`)
+    ///
+    /// 指定した場合は、`read_file`, `from_json`で読み込み、`mpc_table`に格納する。
+    /// 指定しない場合は、`MpcTable::DEFAULT_FILE_PATH`のパラメータ(`MpcTable::default_table`)を使用する。
+    pub mpc_file: Option<String>,
+    /// 組み込みのパラメータの代わりに使用する、MPCのパラメータ
+    #[serde(skip)]
+    pub mpc_table: Option<Arc<MpcTable>>,
+
+    /// キラー手とヒストリー(`MoveHistory`)を、`move_ordering_eval`で使用するか
+    pub use_move_history: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            switch_empties_simple_pvs: 8,
+            switch_empties_simple_nws: 8,
+            switch_empties_last_moves: 4,
+            switch_empties_nega_alpha: 6,
+            switch_empties_move_order: 14,
+            etc_min_empties: 12,
+            move_ordering_eval_level_t: vec![
+                0,
+                0,  0,  0,  0,  0,  0,  0,  0,  1,  1,
+                1,  1,  2,  2,  2,  2,  2,  2,  2,  2,
+                2,  2,  3,  3,  3,  3,  3,  3,  3,  3,
+                3,  3,  3,  3,  3,  3,  3,  3,  4,  4,
+                4,  4,  4,  4,  4,  4,  4,  4,  4,  4,
+                4,  4,  4,  4,  4,  4,  4,  4,  4,  4,
+            ],
+
+            move_ordering_eval_level: 1,
+            move_ordering_eval_level_simple_search: 1,
+            switch_simple_search_level: 6,
+            switch_negaalpha_search_level: 4,
+            etc_min_level: 8,
+
+            mpc_start_level_eval_search: 5,
+            mpc_file: None,
+            mpc_table: MpcTable::default_table(),
+
+            use_move_history: true,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// 探索が正しく動作しないパラメータを検出する。
+    pub fn validate(&self) -> Result<(), String> {
+        if self.move_ordering_eval_level_t.len() != N_EMPTIES_TABLE {
+            return Err(format!("move_ordering_eval_level_t must have {} entries", N_EMPTIES_TABLE));
+        }
+        if !(0..=MAX_SWITCH_EMPTIES_LAST_MOVES).contains(&self.switch_empties_last_moves) {
+            return Err(format!("switch_empties_last_moves must be 0 ~ {}", MAX_SWITCH_EMPTIES_LAST_MOVES));
+        }
+        // move orderingの探索の深さが負の場合、探索が終了しない
+        if self.move_ordering_eval_level < 0
+            || self.move_ordering_eval_level_simple_search < 0
+            || self.move_ordering_eval_level_t.iter().any(|&lv| lv < 0) {
+            return Err("move ordering levels must not be negative".to_string());
+        }
+        Ok(())
+    }
+
+    /// 名前が`name`の整数のパラメータへの参照 (`TUNABLE_PARAMS`, `mpc_start_level_eval_search`)
+    pub fn param_mut(&mut self, name: &str) -> Option<&mut i32> {
+        match name {
+            "switch_empties_simple_pvs"              => Some(&mut self.switch_empties_simple_pvs),
+            "switch_empties_simple_nws"              => Some(&mut self.switch_empties_simple_nws),
+            "switch_empties_last_moves"              => Some(&mut self.switch_empties_last_moves),
+            "switch_empties_nega_alpha"              => Some(&mut self.switch_empties_nega_alpha),
+            "switch_empties_move_order"              => Some(&mut self.switch_empties_move_order),
+            "etc_min_empties"                        => Some(&mut self.etc_min_empties),
+            "move_ordering_eval_level"               => Some(&mut self.move_ordering_eval_level),
+            "move_ordering_eval_level_simple_search" => Some(&mut self.move_ordering_eval_level_simple_search),
+            "switch_simple_search_level"             => Some(&mut self.switch_simple_search_level),
+            "switch_negaalpha_search_level"          => Some(&mut self.switch_negaalpha_search_level),
+            "etc_min_level"                          => Some(&mut self.etc_min_level),
+            "mpc_start_level_eval_search"            => Some(&mut self.mpc_start_level_eval_search),
+            _ => None
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// JSONで書かれたパラメータを読み込む。
+    ///
+    /// `mpc_file`が指定されている場合は、MPCのパラメータも読み込む。
+    pub fn from_json(input: &str) -> io::Result<SearchConfig> {
+        let mut config: SearchConfig = serde_json::from_str(input).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        config.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(path) = &config.mpc_file {
+            let mpc_table = MpcTable::read_file(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+            config.mpc_table = Some(Arc::new(mpc_table));
+        }
+        Ok(config)
+    }
+
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_json())
+    }
+
+    pub fn read_file<P: AsRef<Path>>(path: P) -> io::Result<SearchConfig> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_use_default_values() {
+        let config = SearchConfig::from_json(r#"{"switch_empties_move_order": 16, "use_move_history": false}"#).unwrap();
+        assert_eq!(config.switch_empties_move_order, 16);
+        assert!(!config.use_move_history);
+        assert_eq!(config.etc_min_empties, SearchConfig::default().etc_min_empties);
+
+        let default = SearchConfig::default();
+        assert_eq!(SearchConfig::from_json(&default.to_json()).unwrap(), default);
+    }
+
+    #[test]
+    fn default_config_uses_default_mpc_table() {
+        // `res/mpc.json`が存在する場合のみ、`Some`となる
+        let default_table = MpcTable::default_table();
+        assert_eq!(SearchConfig::default().mpc_table, default_table);
+        assert_eq!(SearchConfig::from_json("{}").unwrap().mpc_table, default_table);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        assert!(SearchConfig::from_json(r#"{"switch_empties_last_moves": 5}"#).is_err());
+        assert!(SearchConfig::from_json(r#"{"move_ordering_eval_level_t": [1, 2, 3]}"#).is_err());
+        assert!(SearchConfig::from_json(r#"{"move_ordering_eval_level": -1}"#).is_err());
+        assert!(SearchConfig::from_json(r#"{"switch_empties_move_ordr": 16}"#).is_err());
+    }
+
+    #[test]
+    fn tunable_params_are_accessible_by_name() {
+        let mut config = SearchConfig::default();
+        for (name, min, max) in TUNABLE_PARAMS {
+            assert!(min <= max, "{}", name);
+            *config.param_mut(name).unwrap() = min;
+        }
+        assert_eq!(config.etc_min_empties, 8);
+        assert!(config.param_mut("use_move_history").is_none());
+    }
+}
@@ -0,0 +1,253 @@
+use std::time::Duration;
+
+use crate::board::*;
+
+/// 探索の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchType {
+    /// 評価関数による探索 (`eval_solver`)
+    Midgame,
+    /// 勝敗のみを求める完全読み (`winning_solver`)
+    Wld,
+    /// 石差を求める完全読み (`perfect_solver`)
+    Exact,
+}
+
+/// 評価値の種類
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScoreBound {
+    /// 正確な値
+    Exact,
+    /// 下限 (真の値は、この値以上)
+    Lower,
+    /// 上限 (真の値は、この値以下)
+    Upper,
+}
+
+/// 探索の途中経過
+#[derive(Clone, Debug)]
+pub struct SearchProgress {
+    /// 探索の深さ (完全読みの場合は空きマス数)
+    pub depth: i32,
+    pub selectivity_lv: i32,
+    /// 評価値の確からしさ (%) (`selectivity_lv`に対応する`SELECTIVITY`の値。MPCを行わない場合は100)
+    pub confidence: i32,
+    /// 最善手 (置換表から求められない場合は`None`)
+    pub best_move: Option<u8>,
+    /// 最善手の評価値 (`SearchType::Wld`の場合は、勝ち:1、引き分け:0、負け:-1)
+    pub score: i32,
+    /// 最善応手手順 (置換表から求めるため、途中で途切れることがある)
+    pub pv: Vec<u8>,
+    /// 探索したノード数 (`eval_search_node_count + perfect_search_node_count`)
+    pub node_count: u64,
+    /// 探索開始からの経過時間
+    pub elapsed: Duration,
+}
+
+/// 探索の進行状況を表すイベント
+pub enum SearchEvent<'a> {
+    /// 探索の開始
+    SearchStarted {
+        board: &'a Board,
+        search_type: SearchType,
+    },
+    /// 反復の開始
+    ///
+    /// 本探索の前に行う、浅い探索(move ordering のための探索)も1回の反復として通知される。
+    IterationStarted {
+        depth: i32,
+        selectivity_lv: i32,
+    },
+    /// ルートの着手1手の探索の終了
+    ///
+    /// アスピレーションウィンドウの再探索を行った場合は、同じ反復の中で同じ手が複数回通知される。
+    RootMoveSearched {
+        put_place: u8,
+        score: i32,
+        bound: ScoreBound,
+    },
+    /// 反復の終了
+    IterationFinished(&'a SearchProgress),
+    /// 探索の終了
+    SearchFinished(&'a SearchProgress),
+}
+
+/// 探索の進行状況を受け取るオブザーバー
+///
+/// `FnMut(&SearchEvent)`を実装する関数・クロージャも、オブザーバーとして使用できる。
+pub trait SearchObserver {
+    fn notify(&mut self, event: &SearchEvent);
+}
+
+impl<F: FnMut(&SearchEvent)> SearchObserver for F {
+    fn notify(&mut self, event: &SearchEvent) {
+        self(event)
+    }
+}
+
+/// 何もしないオブザーバー
+pub struct NullObserver;
+
+impl SearchObserver for NullObserver {
+    fn notify(&mut self, _event: &SearchEvent) {}
+}
+
+/// 探索の進行状況を標準出力に表示するオブザーバー (ソルバーの`print_log`で使用される)
+#[derive(Default)]
+pub struct StdoutObserver {
+    search_type: Option<SearchType>,
+}
+
+impl StdoutObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn score_to_string(&self, score: i32, bound: ScoreBound) -> String {
+        if self.search_type == Some(SearchType::Wld) {
+            return match (score.signum(), bound) {
+                (1, _) => "Win",
+                (0, ScoreBound::Exact) => "Draw",
+                (0, _) => "Draw or Lose",
+                _ => "Lose",
+            }.to_string();
+        }
+        let bound = match bound {
+            ScoreBound::Exact => "",
+            ScoreBound::Lower => ">= ",
+            ScoreBound::Upper => "<= ",
+        };
+        format!("{}{}{}", bound, if score > 0 {"+"} else {""}, score)
+    }
+}
+
+fn moves_to_string(moves: &[u8]) -> String {
+    moves.iter().map(|&m| Board::move_bit_to_str(1 << m).unwrap()).collect()
+}
+
+impl SearchObserver for StdoutObserver {
+    fn notify(&mut self, event: &SearchEvent) {
+        match event {
+            SearchEvent::SearchStarted { board, search_type } => {
+                self.search_type = Some(*search_type);
+                println!("my_turn: {}", if board.next_turn == Board::BLACK {"Black"} else {"White"});
+                println!("depth: {}", board.empties_count());
+                board.print_board();
+            },
+            SearchEvent::IterationStarted { depth, selectivity_lv } => {
+                println!("iteration: depth {}, selectivity {}", depth, selectivity_lv);
+            },
+            SearchEvent::RootMoveSearched { put_place, score, bound } => {
+                println!(" put: {}, score: {}", Board::move_bit_to_str(1 << put_place).unwrap(), self.score_to_string(*score, *bound));
+            },
+            SearchEvent::IterationFinished(progress) => {
+                println!("depth: {}, selectivity: {}, best move: {}, score: {} @ {}%, pv: {}, nodes: {}, time: {:?}",
+                    progress.depth, progress.selectivity_lv,
+                    progress.best_move.map(|m| Board::move_bit_to_str(1 << m).unwrap()).unwrap_or("-".to_string()),
+                    self.score_to_string(progress.score, ScoreBound::Exact), progress.confidence, moves_to_string(&progress.pv),
+                    progress.node_count, progress.elapsed);
+            },
+            SearchEvent::SearchFinished(progress) => {
+                println!("best move: {}, score: {}",
+                    progress.best_move.map(|m| Board::move_bit_to_str(1 << m).unwrap()).unwrap_or("-".to_string()),
+                    self.score_to_string(progress.score, ScoreBound::Exact));
+                println!("pv: {}", moves_to_string(&progress.pv));
+                println!("searched nodes: {}, time: {:?}, nps: {:.0}",
+                    progress.node_count, progress.elapsed, progress.node_count as f64 / progress.elapsed.as_secs_f64());
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::*;
+    use crate::t_table::*;
+    use crate::eval::*;
+    use crate::mpc::NO_MPC;
+    use crate::search_config::SearchConfig;
+
+    /// イベントの種類と、最後の`SearchFinished`の内容を記録する。
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<&'static str>,
+        n_root_moves: usize,
+        finished: Option<SearchProgress>,
+    }
+
+    impl SearchObserver for Recorder {
+        fn notify(&mut self, event: &SearchEvent) {
+            match event {
+                SearchEvent::SearchStarted { .. } => self.events.push("search_started"),
+                SearchEvent::IterationStarted { .. } => {
+                    self.events.push("iteration_started");
+                    self.n_root_moves = 0;
+                },
+                SearchEvent::RootMoveSearched { .. } => self.n_root_moves += 1,
+                SearchEvent::IterationFinished(_) => self.events.push("iteration_finished"),
+                SearchEvent::SearchFinished(progress) => {
+                    self.events.push("search_finished");
+                    self.finished = Some((*progress).clone());
+                },
+            }
+        }
+    }
+
+    fn check_events(recorder: &Recorder, board: &Board, result: &SolverResult) {
+        assert_eq!(recorder.events.first(), Some(&"search_started"));
+        assert_eq!(recorder.events.last(), Some(&"search_finished"));
+        for pair in recorder.events[1..recorder.events.len() - 1].chunks(2) {
+            assert_eq!(pair, ["iteration_started", "iteration_finished"]);
+        }
+        // アスピレーションウィンドウの再探索では、同じ手が再び通知される
+        assert!(recorder.n_root_moves >= board.put_able().count_ones() as usize);
+
+        let progress = recorder.finished.as_ref().unwrap();
+        assert_eq!(progress.best_move.map(|m| 1 << m), Some(result.best_move));
+        assert_eq!(progress.score, result.eval);
+        assert_eq!(progress.pv.first(), progress.best_move.as_ref());
+        assert!(progress.node_count >= result.node_count);
+    }
+
+    #[test]
+    fn solvers_notify_observer() {
+        let mut board = Board::new();
+        for put_place in [F5, D6, C3, D3, C4, F4, F6, F3, E6, E7] {
+            board.put_piece_fast(1 << put_place);
+        }
+        let mut evaluator = Evaluator::new();
+
+        let mut recorder = Recorder::default();
+        let Ok(result) = eval_solver_with_observer(&board, 8, NO_MPC, &mut TranspositionTable::new(), &mut evaluator, &SearchConfig::default(), &mut recorder) else {panic!()};
+        check_events(&recorder, &board, &result);
+        // 浅い探索 (lv - 3) と本探索
+        assert_eq!(recorder.events.len(), 6);
+
+        while board.empties_count() > 10 {
+            let legal_moves = board.put_able();
+            if legal_moves == 0 {
+                board.pass();
+                continue;
+            }
+            board.put_piece_fast(legal_moves & legal_moves.wrapping_neg());
+        }
+        if board.put_able() == 0 {
+            board.pass();
+        }
+
+        let mut recorder = Recorder::default();
+        let Ok(result) = perfect_solver_with_observer(&board, NO_MPC, &mut TranspositionTable::new(), &mut evaluator, &SearchConfig::default(), &mut recorder) else {panic!()};
+        check_events(&recorder, &board, &result);
+
+        let mut recorder = Recorder::default();
+        let Ok(result) = winning_solver_with_observer(&board, &mut TranspositionTable::new(), &mut evaluator, &SearchConfig::default(), &mut recorder) else {panic!()};
+        assert_eq!(recorder.events, ["search_started", "iteration_started", "iteration_finished", "search_finished"]);
+        assert_eq!(recorder.finished.unwrap().score, result.eval);
+
+        // クロージャもオブザーバーとして使用できる
+        let mut n_events = 0;
+        let Ok(_) = eval_solver_with_observer(&board, 4, NO_MPC, &mut TranspositionTable::new(), &mut evaluator, &SearchConfig::default(), &mut |_: &SearchEvent| n_events += 1) else {panic!()};
+        assert!(n_events > 0);
+    }
+}
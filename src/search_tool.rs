@@ -0,0 +1,106 @@
+use crate::board::*;
+use crate::book::*;
+use crate::solver::*;
+use crate::t_table::*;
+use crate::eval::*;
+use crate::mpc::SELECTIVITY;
+use crate::search_config::*;
+use crate::search_observer::*;
+
+/// コマンドラインから、棋譜の局面を探索する。
+///
+/// ```text
+/// deft_reversi_engine search [--moves f5d6c3] [--lv 10] [--selectivity 3] [--nodes 1000000] [--ladder] [--wld]
+///                            [--config config.json] [--log]
+/// ```
+///
+/// `--wld`を指定した場合は、`wld_all_moves`で全ての合法手の勝敗を求める。 (`--selectivity 0`で正確な分類)
+/// `--ladder`を指定した場合は、`selectivity_ladder_solver`で完全読みを行い、
+/// 確からしさを`--selectivity`から100%まで上げながら、各段階の結果を表示する。
+/// `--nodes`を指定した場合は、`node_limited_solver`で深さ`--lv`まで反復深化を行い、ノード数で探索を打ち切る。
+/// ノード数で打ち切った探索は環境に依存しないため、異なるマシンでも同じ手を返す。
+/// `--config`を指定した場合は、探索のパラメータ(`SearchConfig`)をJSONファイルから読み込む。
+pub fn search_cli(args: &[String]) -> Result<(), String> {
+    let mut transcript = String::new();
+    let mut lv = 10;
+    let mut selectivity_lv = 3;
+    let mut node_limit = None;
+    let mut ladder = false;
+    let mut wld = false;
+    let mut print_log = false;
+    let mut config = SearchConfig::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--moves"       => transcript = value()?.clone(),
+            "--lv"          => lv = value()?.parse().map_err(|_| "invalid lv")?,
+            "--selectivity" => selectivity_lv = value()?.parse().map_err(|_| "invalid selectivity")?,
+            "--nodes"       => node_limit = Some(value()?.parse().map_err(|_| "invalid node count")?),
+            "--config"      => {
+                let path = value()?;
+                config = SearchConfig::read_file(path).map_err(|e| format!("{}: {}", path, e))?;
+            },
+            "--ladder"      => ladder = true,
+            "--wld"         => wld = true,
+            "--log"         => print_log = true,
+            _ => return Err(format!("unknown option: {}", arg))
+        }
+    }
+    if !(0..SELECTIVITY.len() as i32).contains(&selectivity_lv) {
+        return Err(format!("selectivity must be 0 ~ {}", SELECTIVITY.len() - 1));
+    }
+
+    let moves = if transcript.is_empty() {Some(Vec::new())} else {parse_transcript(&transcript)};
+    let moves = moves.ok_or(format!("invalid moves: {}", transcript))?;
+    let mut board = Board::new();
+    for &put_place in moves.iter() {
+        board = Book::node_board(&board);
+        board.put_piece(1 << put_place).map_err(|_| format!("invalid moves: {}", transcript))?;
+    }
+    let board = Book::node_board(&board);
+
+    let mut evaluator = Evaluator::read_file().map_err(|e| e.to_string())?;
+    let mut t_table = TranspositionTable::new();
+    let mut stdout_observer = StdoutObserver::new();
+
+    if wld {
+        let observer: &mut dyn SearchObserver = if print_log {&mut stdout_observer} else {&mut NullObserver};
+        let results = match wld_all_moves_with_observer(&board, selectivity_lv, &mut t_table, &mut evaluator, &config, observer) {
+            Ok(results) => results,
+            Err(SolverErr::NoMove) => return Err("no legal moves (game over)".to_string()),
+            Err(SolverErr::InconsistentResult) => return Err("inconsistent search result (use a lower --selectivity)".to_string()),
+        };
+        for result in results.iter() {
+            println!("{}: {:?}, nodes: {}", Board::move_bit_to_str(1 << result.put_place).unwrap(), result.wld, result.node_count);
+        }
+        return Ok(());
+    }
+
+    let n_empties = board.empties_count();
+    // ログを表示しない場合も、完全読みの各段階の結果は表示する
+    let mut stage_observer = |event: &SearchEvent| {
+        if let SearchEvent::IterationFinished(progress) = event {
+            if progress.depth == n_empties {
+                println!("stage: best move: {}, score: {}{} @ {}%",
+                    progress.best_move.map(|m| Board::move_bit_to_str(1 << m).unwrap()).unwrap_or("-".to_string()),
+                    if progress.score > 0 {"+"} else {""}, progress.score, progress.confidence);
+            }
+        }
+    };
+    let observer: &mut dyn SearchObserver = 
+        if print_log {&mut stdout_observer} else if ladder {&mut stage_observer} else {&mut NullObserver};
+    let result = match (ladder, node_limit) {
+        (true, _) => selectivity_ladder_solver_with_observer(&board, selectivity_lv, &mut t_table, &mut evaluator, &config, observer),
+        (false, Some(node_limit)) => node_limited_solver_with_observer(&board, lv, selectivity_lv, node_limit, &mut t_table, &mut evaluator, &config, observer),
+        (false, None) => eval_solver_with_observer(&board, lv, selectivity_lv, &mut t_table, &mut evaluator, &config, observer)
+    };
+    let Ok(result) = result else {
+        return Err("no legal moves (game over)".to_string());
+    };
+
+    println!("best move: {}, score: {}{}, nodes: {}",
+        Board::move_bit_to_str(result.best_move).unwrap(), if result.eval > 0 {"+"} else {""}, result.eval, result.node_count);
+    Ok(())
+}
@@ -4,6 +4,10 @@ use crate::eval_search::*;
 use crate::search::*;
 use crate::t_table::*;
 use crate::eval::*;
+use crate::book::*;
+use crate::search_observer::*;
+use crate::search_config::*;
+use crate::mpc::{NO_MPC, SELECTIVITY_LV_MAX};
 
 pub struct SolverResult {
     pub best_move: u64,
@@ -13,7 +17,38 @@ pub struct SolverResult {
 }
 
 pub enum SolverErr {
+    /// 合法手が存在しない
     NoMove,
+    /// 同じ局面の探索結果が矛盾した (MPCによる枝刈りの誤りなど)
+    InconsistentResult,
+}
+
+/// 勝敗の分類
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wld {
+    Loss,
+    Draw,
+    Win,
+}
+
+impl Wld {
+    /// 勝ち:1、引き分け:0、負け:-1 (`winning_solver`の評価値)
+    pub fn score(self) -> i32 {
+        match self {
+            Wld::Loss => -1,
+            Wld::Draw => 0,
+            Wld::Win => 1,
+        }
+    }
+}
+
+/// `wld_all_moves`の、合法手1手の結果
+#[derive(Clone, Debug)]
+pub struct WldMoveResult {
+    pub put_place: u8,
+    pub wld: Wld,
+    /// この合法手の探索に要したノード数
+    pub node_count: u64,
 }
 
 const SCORE_INF: i32 = i8::MAX as i32;
@@ -36,9 +71,12 @@ const MOVE_ORDERING_EVAL_LEVEL: i32 = 8;
 /// 合法手が存在しない場合は、`SolverErr::NoMove`エラーが返されます。
 ///
 /// # 例
-/// ```
+/// (初期盤面の完全読みは終わらないため、コンパイルのみ確認する)
+/// ```no_run
+/// use deft_reversi_engine::*;
+///
 /// let board = Board::new(); // 初期盤面の生成
-/// match perfect_solver(&board, true) {
+/// match perfect_solver(&board, true, 0, &mut TranspositionTable::new(), &mut Evaluator::new()) {
 ///     Ok(result) => println!("Best move: {}, Score: {}", result.best_move, result.eval),
 ///     Err(SolverErr::NoMove) => println!("No legal moves available."),
 ///     _ => println!("An error occurred during the search.")
@@ -46,89 +84,69 @@ const MOVE_ORDERING_EVAL_LEVEL: i32 = 8;
 /// ```
 ///
 pub fn perfect_solver(board: &Board, print_log: bool, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if print_log {
+        perfect_solver_with_observer(board, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        perfect_solver_with_observer(board, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
+    }
+}
+
+/// `perfect_solver`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+pub fn perfect_solver_with_observer(board: &Board, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator: &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<SolverResult, SolverErr>
 {
     let legal_moves = board.put_able();
     if legal_moves == 0 {
         return Err(SolverErr::NoMove)
     }
     let mut search = Search::new(board, selectivity_lv, t_table, evaluator);
-    
-    if print_log {
-        println!("my_turn: {}", if board.next_turn == Board::BLACK {"Black"} else {"White"});
-        println!("depth: {}", board.empties_count());
-        board.print_board();
-    };
-
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Exact });
 
-    if print_log {print!("move_ordering....");};
+    // 直前の浅い探索の評価値を、本探索のアスピレーションウィンドウの中心とする
+    let mut estimate = None;
 
     if board.empties_count() > 8 {
-        pvs_eval(board, -SCORE_INF ,SCORE_INF, 6, &mut search);
+        estimate = Some(iterate_eval(board, 6, &mut search));
     }
     if board.empties_count() > 10 {
-        pvs_eval(board, -SCORE_INF ,SCORE_INF, 8, &mut search);
+        estimate = Some(iterate_eval(board, 8, &mut search));
     }
     if board.empties_count() > 12 {
-        pvs_eval(board, -SCORE_INF ,SCORE_INF, 10, &mut search);
+        estimate = Some(iterate_eval(board, 10, &mut search));
     }
 
 
     if board.empties_count() > 20 {
         let main_selectivity_lv = selectivity_lv;
         search.selectivity_lv = 5;
-        pvs_eval(board, -SCORE_INF, SCORE_INF,16, &mut search);
+        estimate = Some(iterate_eval(board, 16, &mut search));
         search.selectivity_lv = main_selectivity_lv;
     }
     if search.selectivity_lv < 3 {
         let main_selectivity_lv = selectivity_lv;
         search.selectivity_lv = 5;
-        pvs_perfect(board, -SCORE_INF, SCORE_INF, &mut search);
+        estimate = Some(iterate_perfect(board, &mut search));
         search.selectivity_lv = main_selectivity_lv;
     }
 
-    let mut put_boards = 
+    let put_boards = 
         if board.empties_count() < MOVE_ORDERING_EVAL_LEVEL + 2 {
             get_put_boards(board, legal_moves)
         } else {
-            move_ordering_eval(board, legal_moves, 8,  &mut search)
+            move_ordering_eval(board, legal_moves, 8,  &mut search);
+            search.move_list(board.empties_count()).clone()
         };
-    if print_log {println!("OK");};
 
-    let mut alpha = -SCORE_INF;
-    let beta = SCORE_INF;
-    let mut put_place_best_score ;
-    
-    let mut put_boards_iter = put_boards.iter_mut();
-    let first_child_board = put_boards_iter.next().unwrap();
-    alpha = -pvs_perfect(&first_child_board.board, -beta, -alpha, &mut search);
-    put_place_best_score = first_child_board.put_place;
-    if print_log { 
-        println!("put: {}, nega scout score: {}",Board::move_bit_to_str(1 << put_place_best_score).unwrap(), alpha);
-    };
+    let n_empties = board.empties_count();
+    search.notify(&SearchEvent::IterationStarted { depth: n_empties, selectivity_lv: search.selectivity_lv });
 
-    for put_board in put_boards_iter {
-        let current_put_board = &put_board.board;
-        let put_place = put_board.put_place;
-        let mut score = -nws_perfect(current_put_board, -alpha - 1, &mut search);
-        if score > alpha {
-            if print_log { 
-                println!(" put: {}, null window score: {} => reserch [{},{}]",Board::move_bit_to_str(1 << put_place).unwrap(), score, alpha, beta);
-            }
-            score = -pvs_perfect(current_put_board, -beta, -alpha, &mut search);
-            if score > alpha {
-                alpha = score;
-                put_place_best_score = put_place;
-            }
-        }
-        if print_log { 
-            println!("put: {}, nega scout score: {}",Board::move_bit_to_str(1 << put_place).unwrap(), score);
-        }
-    }
+    let Some((put_place_best_score, alpha)) = aspiration_root(&put_boards, estimate, &mut search, pvs_perfect, nws_perfect) else {unreachable!()};
 
-    if print_log { 
-        println!("best move: {}, score: {}{}",Board::move_bit_to_str(1 << put_place_best_score).unwrap(), if alpha > 0 {"+"} else {""},alpha);
-        println!("searched nodes: {}\nsearched leaf nodes: {}", search.perfect_search_node_count, search.perfect_search_leaf_node_count);
-    }
+    let progress = search.progress(n_empties, Some(put_place_best_score), alpha);
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    search.notify(&SearchEvent::SearchFinished(&progress));
 
     Ok(SolverResult{
         best_move: 1 << put_place_best_score,
@@ -138,6 +156,104 @@ pub fn perfect_solver(board: &Board, print_log: bool, selectivity_lv: i32, t_tab
     })
 }
 
+/// move ordering のための評価関数による浅い探索を、1回の反復として行う。
+fn iterate_eval(board: &Board, lv: i32, search: &mut Search) -> i32
+{
+    search.notify(&SearchEvent::IterationStarted { depth: lv, selectivity_lv: search.selectivity_lv });
+    let score = pvs_eval(board, -SCORE_INF, SCORE_INF, lv, search);
+    let progress = search.progress(lv, None, score);
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    score
+}
+
+/// move ordering のための完全読み(MPCを使用)を、1回の反復として行う。
+fn iterate_perfect(board: &Board, search: &mut Search) -> i32
+{
+    let n_empties = board.empties_count();
+    search.notify(&SearchEvent::IterationStarted { depth: n_empties, selectivity_lv: search.selectivity_lv });
+    let score = pvs_perfect(board, -SCORE_INF, SCORE_INF, search);
+    let progress = search.progress(n_empties, None, score);
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    score
+}
+
+/// MPCの確からしさを段階的に上げながら完全読みを行い、最後に正確な評価値を求める。
+///
+/// 選択的探索のレベルを`start_selectivity_lv`から1ずつ下げ(確からしさ 68% → 77% → ... → 100%)、
+/// 各段階の完全読みを1回の反復として、最善手・評価値・確からしさ(`SearchProgress::confidence`)を`observer`に通知する。
+/// 確からしさの低い結果("+4 @ 95%"など)をすぐに表示し、正確な結果へと更新していく用途に使用する。
+///
+/// 置換表は全ての段階で共有する。
+/// 各段階では、前の段階の最善手を最初に探索し、前の段階の評価値をアスピレーションウィンドウの中心とする。
+///
+/// # 引数
+/// * `start_selectivity_lv` - 最初の段階の選択的探索のレベル (`SELECTIVITY_LV_MAX`より大きい場合は`SELECTIVITY_LV_MAX`)
+/// * その他の引数は`perfect_solver`と同じ
+///
+/// # 戻り値
+/// * 最後の段階(MPCなし)の結果 (`perfect_solver`と同じ、正確な評価値)
+/// * 合法手が存在しない場合は、`SolverErr::NoMove`
+pub fn selectivity_ladder_solver(board: &Board, start_selectivity_lv: i32, print_log: bool, t_table: &mut TranspositionTable, evaluator: &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if print_log {
+        selectivity_ladder_solver_with_observer(board, start_selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        selectivity_ladder_solver_with_observer(board, start_selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
+    }
+}
+
+/// `selectivity_ladder_solver`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+pub fn selectivity_ladder_solver_with_observer(board: &Board, start_selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator: &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<SolverResult, SolverErr>
+{
+    let legal_moves = board.put_able();
+    if legal_moves == 0 {
+        return Err(SolverErr::NoMove)
+    }
+    let start_selectivity_lv = start_selectivity_lv.clamp(NO_MPC, SELECTIVITY_LV_MAX);
+
+    let mut search = Search::new(board, start_selectivity_lv, t_table, evaluator);
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Exact });
+
+    let n_empties = board.empties_count();
+    let mut estimate = if n_empties > 8 {Some(iterate_eval(board, 6, &mut search))} else {None};
+
+    let mut put_boards = 
+        if n_empties < MOVE_ORDERING_EVAL_LEVEL + 2 {
+            get_put_boards(board, legal_moves)
+        } else {
+            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL, &mut search);
+            search.move_list(n_empties).clone()
+        };
+
+    let mut progress = None;
+    for selectivity_lv in (NO_MPC..=start_selectivity_lv).rev() {
+        search.selectivity_lv = selectivity_lv;
+        search.notify(&SearchEvent::IterationStarted { depth: n_empties, selectivity_lv });
+
+        let Some((put_place_best_score, alpha)) = aspiration_root(&put_boards, estimate, &mut search, pvs_perfect, nws_perfect) else {unreachable!()};
+        let stage_progress = search.progress(n_empties, Some(put_place_best_score), alpha);
+        search.notify(&SearchEvent::IterationFinished(&stage_progress));
+
+        // 次の段階では、この段階の最善手を最初に探索する
+        if let Some(i) = put_boards.iter().position(|p| p.put_place == put_place_best_score) {
+            put_boards[..=i].rotate_right(1);
+        }
+        estimate = Some(alpha);
+        progress = Some(stage_progress);
+    }
+    let Some(progress) = progress else {unreachable!()};
+    search.notify(&SearchEvent::SearchFinished(&progress));
+
+    Ok(SolverResult{
+        best_move: 1 << progress.best_move.unwrap(),
+        eval: progress.score,
+        node_count: search.perfect_search_node_count,
+        leaf_node_count: search.perfect_search_leaf_node_count
+    })
+}
+
 /// オセロの盤面に対する勝利可能性を評価し、最適な手を決定する。
 ///
 /// この関数は、Null Window Search (NWS) アルゴリズムを使用して、
@@ -152,11 +268,22 @@ pub fn perfect_solver(board: &Board, print_log: bool, selectivity_lv: i32, t_tab
 /// `Result<SolverResult, SolverErr>` 型。成功した場合、`SolverResult`オブジェクトが含まれ、
 /// 最適な手とその評価値（勝ち:1、引き分け:0、負け:-1）、探索したノード数、葉ノード数を含みます。
 /// 合法手が存在しない場合は、`SolverErr::NoMove`エラーが返されます。
+/// 探索結果が矛盾した場合は、`SolverErr::InconsistentResult`エラーが返されます。
 ///
 /// # 注記
 /// 探索過程の進行状況や結果の詳細な出力が必要な場合は、print_logパラメータをtrueに設定してください。これにより、
 /// 各手の評価値や探索したノードの数など、探索に関する詳細な情報が出力されます。
 pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if print_log {
+        winning_solver_with_observer(board, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        winning_solver_with_observer(board, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
+    }
+}
+
+/// `winning_solver`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+pub fn winning_solver_with_observer(board: &Board, t_table: &mut TranspositionTable, evaluator : &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<SolverResult, SolverErr>
 {
     let legal_moves = board.put_able();
     if legal_moves == 0 {
@@ -164,21 +291,20 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
     }
 
     let mut search = Search::new(board, 0, t_table, evaluator);
-    
-    if print_log {
-        println!("my_turn: {}", if board.next_turn == Board::BLACK {"Black"} else {"White"});
-        println!("depth: {}", board.empties_count());
-        board.print_board();
-    };
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Wld });
 
-    if print_log {print!("move_ordering....");};
     let mut put_boards = 
         if board.empties_count() < MOVE_ORDERING_EVAL_LEVEL + 2 {
             get_put_boards(board, legal_moves)
         } else {
-            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL,  &mut search)
+            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL,  &mut search);
+            search.move_list(board.empties_count()).clone()
         };
-    if print_log {println!("OK");};
+
+    let n_empties = board.empties_count();
+    search.notify(&SearchEvent::IterationStarted { depth: n_empties, selectivity_lv: search.selectivity_lv });
 
     // [alpha, beta] = [0, 1]
     let mut put_place_best_score = 0;
@@ -191,27 +317,21 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
         let put_place = put_board.put_place;
         let score = -nws_perfect(current_put_board, -beta,&mut search);
         if score > 0 {
-            if print_log { 
-                println!(" put: {}, Win",Board::move_bit_to_str(1 << put_place).unwrap());
-            }
+            search.notify(&SearchEvent::RootMoveSearched { put_place, score: 1, bound: ScoreBound::Lower });
             if eval <= 0 {
                 put_place_best_score = put_place;
                 eval = 1
             };
             break;
         } else if score < 0 {
-            if print_log { 
-                println!(" put: {}, Lose",Board::move_bit_to_str(1 << put_place).unwrap());
-            }
+            search.notify(&SearchEvent::RootMoveSearched { put_place, score: -1, bound: ScoreBound::Upper });
         } else {
             draw_or_lose_board_index.push(i);
             if eval < 0 {
                 put_place_best_score = put_place;
                 eval = 0
             };
-            if print_log { 
-                println!(" put: {}, Draw or Lose", Board::move_bit_to_str(1 << put_place).unwrap());
-            }
+            search.notify(&SearchEvent::RootMoveSearched { put_place, score: 0, bound: ScoreBound::Upper });
         }
     }
 
@@ -225,9 +345,7 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
             let put_place = put_board.put_place;
             let score = -nws_perfect(current_put_board, -beta,&mut search);
             if score == 0 {
-                if print_log { 
-                    println!(" put: {}, Draw", Board::move_bit_to_str(1 << put_place).unwrap());
-                }
+                search.notify(&SearchEvent::RootMoveSearched { put_place, score: 0, bound: ScoreBound::Exact });
                 if eval < 0 {
                     put_place_best_score = put_place;
                     eval = 0
@@ -235,13 +353,10 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
                 break;
 
             } else if score < 0 {
-                if print_log { 
-                    println!(" put: {}, Lose",Board::move_bit_to_str(1 << put_place).unwrap());
-                }
+                search.notify(&SearchEvent::RootMoveSearched { put_place, score: -1, bound: ScoreBound::Upper });
                 eval = -1;
             } else {
-                eprintln!("Error ocurred in winning_solver");
-                panic!()
+                return Err(SolverErr::InconsistentResult);
             }
         }   
     }
@@ -249,10 +364,9 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
         put_place_best_score = put_boards[0].put_place;
     }
 
-    if print_log { 
-        println!("best move: {}, score: {}",Board::move_bit_to_str(1 << put_place_best_score).unwrap(), if eval > 0 {"Win"} else if eval < 0 {"Lose"} else {"Draw"});
-        println!("searched nodes: {}\nsearched leaf nodes: {}", search.perfect_search_node_count, search.perfect_search_leaf_node_count);
-    }
+    let progress = search.progress(n_empties, Some(put_place_best_score), eval);
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    search.notify(&SearchEvent::SearchFinished(&progress));
 
     Ok(SolverResult{
         best_move: 1 << put_place_best_score,
@@ -262,6 +376,103 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
     })
 }
 
+/// 全ての合法手について、着手後の局面の勝敗(勝ち・引き分け・負け)を求める。
+///
+/// `winning_solver`とは異なり、勝ちの手が見つかった後も探索を続け、全ての合法手を分類する。
+/// 終盤の練習問題のように、正解の手を全て求める場合に使用する。
+/// 各合法手について、探索窓`[0, 1]`で勝ちかどうかを調べ、勝ちでない場合は探索窓`[-1, 0]`で引き分けか負けかを調べる。
+/// MPCを使用して2回の探索結果が矛盾した手は、MPCを使用せずに探索し直す。
+///
+/// # 引数
+/// * `selectivity_lv` - 選択的探索のレベル (`NO_MPC`の場合は、MPCを使用せず正確な分類を求める)
+/// * その他の引数は`winning_solver`と同じ
+///
+/// # 戻り値
+/// * 各合法手の`WldMoveResult`を、勝ち・引き分け・負けの順に並べたもの (同じ分類の手は、move orderingの順)
+/// * 合法手が存在しない場合は、`SolverErr::NoMove`
+/// * MPCを使用せずに探索し直しても結果が矛盾した場合は、`SolverErr::InconsistentResult`
+pub fn wld_all_moves(board: &Board, selectivity_lv: i32, print_log: bool, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<Vec<WldMoveResult>, SolverErr>
+{
+    if print_log {
+        wld_all_moves_with_observer(board, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        wld_all_moves_with_observer(board, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
+    }
+}
+
+/// `wld_all_moves`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+pub fn wld_all_moves_with_observer(board: &Board, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator : &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<Vec<WldMoveResult>, SolverErr>
+{
+    let legal_moves = board.put_able();
+    if legal_moves == 0 {
+        return Err(SolverErr::NoMove)
+    }
+
+    let mut search = Search::new(board, selectivity_lv, t_table, evaluator);
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Wld });
+
+    let put_boards = 
+        if board.empties_count() < MOVE_ORDERING_EVAL_LEVEL + 2 {
+            get_put_boards(board, legal_moves)
+        } else {
+            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL,  &mut search);
+            search.move_list(board.empties_count()).clone()
+        };
+
+    let n_empties = board.empties_count();
+    search.notify(&SearchEvent::IterationStarted { depth: n_empties, selectivity_lv: search.selectivity_lv });
+
+    let mut results = Vec::with_capacity(put_boards.len());
+    for put_board in put_boards.iter() {
+        let node_count = search.node_count();
+        let put_place = put_board.put_place;
+
+        let wld = match wld_after_move(&put_board.board, &mut search) {
+            Some(wld) => wld,
+            None if search.selectivity_lv != NO_MPC => {
+                // MPCによる枝刈りの誤りで矛盾した場合は、この手のみMPCを使用せずに探索し直す
+                let main_selectivity_lv = search.selectivity_lv;
+                search.selectivity_lv = NO_MPC;
+                let wld = wld_after_move(&put_board.board, &mut search);
+                search.selectivity_lv = main_selectivity_lv;
+                wld.ok_or(SolverErr::InconsistentResult)?
+            },
+            None => return Err(SolverErr::InconsistentResult)
+        };
+        search.notify(&SearchEvent::RootMoveSearched { put_place, score: wld.score(), bound: ScoreBound::Exact });
+        results.push(WldMoveResult { put_place, wld, node_count: search.node_count() - node_count });
+    }
+    results.sort_by_key(|r| std::cmp::Reverse(r.wld));
+
+    let progress = search.progress(n_empties, Some(results[0].put_place), results[0].wld.score());
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    search.notify(&SearchEvent::SearchFinished(&progress));
+
+    Ok(results)
+}
+
+/// 着手後の局面`board`について、着手した側から見た勝敗を求める。
+///
+/// 探索窓`[0, 1]`で勝ちかどうかを調べ、勝ちでない場合は探索窓`[-1, 0]`で引き分けか負けかを調べる。
+///
+/// # 戻り値
+/// * 2回の探索結果が矛盾した場合は、`None`
+fn wld_after_move(board: &Board, search: &mut Search) -> Option<Wld>
+{
+    // [alpha, beta] = [0, 1]
+    if -nws_perfect(board, -1, search) > 0 {
+        return Some(Wld::Win);
+    }
+    // [alpha, beta] = [-1, 0]
+    match (-nws_perfect(board, 0, search)).signum() {
+        0 => Some(Wld::Draw),
+        -1 => Some(Wld::Loss),
+        _ => None
+    }
+}
+
 /// オセロの盤面に対する評価関数を用いた探索を行い、最適な手を決定する。
 ///
 /// この関数は、評価関数に基づいて盤面のスコアを計算し、
@@ -283,6 +494,16 @@ pub fn winning_solver(board: &Board, print_log: bool, t_table: &mut Transpositio
 /// 探索の深さ (lv) は、盤面の複雑さや求める精度に応じて適切に設定する必要があります。
 /// また、print_logパラメータをtrueに設定することで、探索の進行状況や結果の詳細がコンソールに出力されます。
 pub fn eval_solver(board: &Board, lv: i32, selectivity_lv: i32, print_log: bool, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if print_log {
+        eval_solver_with_observer(board, lv, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        eval_solver_with_observer(board, lv, selectivity_lv, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
+    }
+}
+
+/// `eval_solver`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+pub fn eval_solver_with_observer(board: &Board, lv: i32, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator : &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<SolverResult, SolverErr>
 {
     let legal_moves = board.put_able();
     if legal_moves == 0 {
@@ -290,69 +511,538 @@ pub fn eval_solver(board: &Board, lv: i32, selectivity_lv: i32, print_log: bool,
     }
 
     let mut search = Search::new(board, selectivity_lv, t_table, evaluator);
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Midgame });
     
-    if print_log {
-        println!("my_turn: {}", if board.next_turn == Board::BLACK {"Black"} else {"White"});
-        println!("depth: {}", board.empties_count());
-        // board.print_board();
-    };
-    
-    
-    if print_log {println!("move_ordering....");};
-    
+    let estimate = if lv > 6 {Some(iterate_eval(board, lv - 3, &mut search))} else {None};
+
+    let Some(progress) = eval_root_search(board, legal_moves, lv, estimate, &mut search) else {unreachable!()};
+    search.notify(&SearchEvent::SearchFinished(&progress));
     
-    if lv > 6 {
-        pvs_eval(board, -SCORE_INF, SCORE_INF, lv - 3, &mut search);
-    }
+    Ok(SolverResult{
+        best_move: 1 << progress.best_move.unwrap(),
+        eval: progress.score,
+        node_count: search.eval_search_node_count,
+        leaf_node_count: search.eval_search_leaf_node_count
+    })
+}
 
+/// ルートの全ての合法手を`lv`の深さで探索し、1回の反復として通知する。
+///
+/// # 戻り値
+/// * 探索ノード数が上限に達し、探索を打ち切った場合は`None`
+fn eval_root_search(board: &Board, legal_moves: u64, lv: i32, estimate: Option<i32>, search: &mut Search) -> Option<SearchProgress>
+{
     let put_boards = 
         if lv - 3 <= 0 {
             get_put_boards(board, legal_moves)
         } else {
-            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL.min(lv - 4),  &mut search)
+            move_ordering_eval(board, legal_moves, MOVE_ORDERING_EVAL_LEVEL.min(lv - 4),  search);
+            search.move_list(board.empties_count()).clone()
         };
-    if print_log {println!("OK");};
 
-    let mut alpha = -SCORE_INF;
-    let beta = SCORE_INF;
-    let mut put_place_best_score ;
-    
+    search.notify(&SearchEvent::IterationStarted { depth: lv, selectivity_lv: search.selectivity_lv });
+
+    let (put_place_best_score, alpha) = aspiration_root(&put_boards, estimate, search,
+        |board, alpha, beta, search| pvs_eval(board, alpha, beta, lv - 1, search),
+        |board, alpha, search| nws_eval(board, alpha, lv - 1, search))?;
+
+    let progress = search.progress(lv, Some(put_place_best_score), alpha);
+    search.notify(&SearchEvent::IterationFinished(&progress));
+    Some(progress)
+}
+
+/// アスピレーションウィンドウの初期の幅 (推定値 ± `ASPIRATION_WINDOW`)
+const ASPIRATION_WINDOW: i32 = 4;
+
+/// ルートの全ての合法手を、探索窓`[alpha, beta]`でPVSにより探索する。
+///
+/// # 引数
+/// * `pvs` - 子ノードを探索するPVSの関数 (`pvs_eval`または`pvs_perfect`)
+/// * `nws` - 子ノードを探索するNWSの関数 (`nws_eval`または`nws_perfect`)
+///
+/// # 戻り値
+/// * `(最善手, 評価値)`
+///   * 評価値が`alpha`以下の場合は真の値の上限、`beta`以上の場合は真の値の下限
+/// * 探索ノード数が上限に達し、探索を打ち切った場合は`None`
+fn pvs_root<P, N>(put_boards: &[PutBoard], alpha: i32, beta: i32, search: &mut Search, pvs: P, nws: N) -> Option<(u8, i32)>
+where
+    P: Fn(&Board, i32, i32, &mut Search) -> i32,
+    N: Fn(&Board, i32, &mut Search) -> i32
+{
+    let mut this_node_alpha = alpha;
     let mut put_boards_iter = put_boards.iter();
+
     let first_child_board = put_boards_iter.next().unwrap();
-    alpha = -pvs_eval(&first_child_board.board, -beta, -alpha, lv - 1, &mut search);
-    put_place_best_score = first_child_board.put_place;
-    if print_log { 
-        println!("put: {}, nega scout score: {}",Board::move_bit_to_str(1 << put_place_best_score).unwrap(), alpha);
-    };
+    let mut best_score = -pvs(&first_child_board.board, -beta, -this_node_alpha, search);
+    let mut best_move = first_child_board.put_place;
+    search.notify(&SearchEvent::RootMoveSearched { put_place: best_move, score: best_score, bound: score_bound(best_score, alpha, beta) });
+    if best_score > this_node_alpha {this_node_alpha = best_score};
 
     for put_board in put_boards_iter {
+        if best_score >= beta || search.node_limit_exceeded() {
+            break;
+        }
         let current_put_board = &put_board.board;
         let put_place = put_board.put_place;
-        let mut score = -nws_eval(current_put_board, -alpha - 1, lv - 1, &mut search);
-        if score > alpha {
-            if print_log { 
-                println!(" put: {}, null window score: {} => reserch [{},{}]",Board::move_bit_to_str(1 << put_place).unwrap(), score, alpha, beta);
+        let mut score = -nws(current_put_board, -this_node_alpha - 1, search);
+        let mut bound = ScoreBound::Upper;
+        if score > this_node_alpha {
+            if score < beta {
+                score = -pvs(current_put_board, -beta, -this_node_alpha, search);
             }
-            score = -pvs_eval(current_put_board, -beta, -alpha, lv - 1, &mut search);
-            if score > alpha {
-                alpha = score;
-                put_place_best_score = put_place;
+            bound = score_bound(score, this_node_alpha, beta);
+            if score > best_score {
+                best_score = score;
+                best_move = put_place;
             }
+            if score > this_node_alpha {this_node_alpha = score};
         }
-        if print_log { 
-            println!("put: {}, nega scout score: {}",Board::move_bit_to_str(1 << put_place).unwrap(), score);
+        search.notify(&SearchEvent::RootMoveSearched { put_place, score, bound });
+    }
+
+    if search.node_limit_exceeded() {
+        return None;
+    }
+    Some((best_move, best_score))
+}
+
+fn score_bound(score: i32, alpha: i32, beta: i32) -> ScoreBound {
+    if score <= alpha {
+        ScoreBound::Upper
+    } else if score >= beta {
+        ScoreBound::Lower
+    } else {
+        ScoreBound::Exact
+    }
+}
+
+/// 推定値を中心としたアスピレーションウィンドウで、ルートの探索(`pvs_root`)を行う。
+///
+/// 探索結果が窓の外になった場合(fail-low / fail-high)は、その側の窓の幅を2倍に広げて再探索する。
+/// 推定値がない場合は、`[-SCORE_INF, SCORE_INF]`で探索する。
+///
+/// # 引数
+/// * `estimate` - 評価値の推定値 (前回の反復や、浅い探索の評価値)
+///
+/// # 戻り値
+/// * `(最善手, 評価値)`
+/// * 探索ノード数が上限に達し、探索を打ち切った場合は`None`
+fn aspiration_root<P, N>(put_boards: &[PutBoard], estimate: Option<i32>, search: &mut Search, pvs: P, nws: N) -> Option<(u8, i32)>
+where
+    P: Fn(&Board, i32, i32, &mut Search) -> i32 + Copy,
+    N: Fn(&Board, i32, &mut Search) -> i32 + Copy
+{
+    let Some(estimate) = estimate else {
+        return pvs_root(put_boards, -SCORE_INF, SCORE_INF, search, pvs, nws);
+    };
+
+    let mut delta_low = ASPIRATION_WINDOW;
+    let mut delta_high = ASPIRATION_WINDOW;
+    loop {
+        let alpha = (estimate - delta_low).max(-SCORE_INF);
+        let beta = (estimate + delta_high).min(SCORE_INF);
+        let (best_move, score) = pvs_root(put_boards, alpha, beta, search, pvs, nws)?;
+
+        if score <= alpha && alpha > -SCORE_INF {
+            delta_low *= 2;
+        } else if score >= beta && beta < SCORE_INF {
+            delta_high *= 2;
+        } else {
+            return Some((best_move, score));
         }
     }
+}
 
-    if print_log { 
-        println!("best move: {}, score: {}{}",Board::move_bit_to_str(1 << put_place_best_score).unwrap(), if alpha > 0 {"+"} else {""},alpha);
-        println!("searched nodes: {}\nsearched leaf nodes: {}", search.eval_search_node_count, search.eval_search_leaf_node_count);
+/// 探索ノード数の上限を指定して、評価関数を用いた反復深化による探索を行う。
+///
+/// 深さ1から`max_lv`まで`eval_solver`と同じルートの探索を繰り返し、
+/// 探索ノード数(`eval_search_node_count + perfect_search_node_count`)が`node_limit`に達した時点で打ち切る。
+/// 打ち切った反復の結果は使用せず、最後に完了した反復の結果を返す。
+/// ノード数は環境に依存しないため、同じ引数(と同じ状態の置換表)であれば、どの環境でも同じ手を返す。
+///
+/// # 引数
+/// * `max_lv` - 探索の深さの上限
+/// * `node_limit` - 探索ノード数の上限 (深さ1の探索は、上限に関わらず完了させる)
+/// * その他の引数は`eval_solver`と同じ
+///
+/// # 戻り値
+/// * 合法手が存在しない場合は、`SolverErr::NoMove`
+pub fn node_limited_solver(board: &Board, max_lv: i32, selectivity_lv: i32, node_limit: u64, print_log: bool, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if print_log {
+        node_limited_solver_with_observer(board, max_lv, selectivity_lv, node_limit, t_table, evaluator, &SearchConfig::default(), &mut StdoutObserver::new())
+    } else {
+        node_limited_solver_with_observer(board, max_lv, selectivity_lv, node_limit, t_table, evaluator, &SearchConfig::default(), &mut NullObserver)
     }
-    
+}
+
+/// `node_limited_solver`と同じ探索を、パラメータ`config`で行い、探索の進行状況を`observer`に通知する。
+#[allow(clippy::too_many_arguments)]
+pub fn node_limited_solver_with_observer(board: &Board, max_lv: i32, selectivity_lv: i32, node_limit: u64, t_table: &mut TranspositionTable, evaluator : &mut Evaluator, config: &SearchConfig, observer: &mut dyn SearchObserver) -> Result<SolverResult, SolverErr>
+{
+    let legal_moves = board.put_able();
+    if legal_moves == 0 {
+        return Err(SolverErr::NoMove)
+    }
+
+    let mut search = Search::new(board, selectivity_lv, t_table, evaluator);
+    search.set_config(config);
+    search.observer = Some(observer);
+    search.notify(&SearchEvent::SearchStarted { board, search_type: SearchType::Midgame });
+
+    let Some(mut progress) = eval_root_search(board, legal_moves, 1, None, &mut search) else {unreachable!()};
+    search.node_limit = node_limit;
+    for lv in 2..=max_lv {
+        match eval_root_search(board, legal_moves, lv, Some(progress.score), &mut search) {
+            Some(p) => progress = p,
+            None => break
+        }
+    }
+    search.notify(&SearchEvent::SearchFinished(&progress));
+
     Ok(SolverResult{
-        best_move: 1 << put_place_best_score,
-        eval: alpha,
-        node_count: search.eval_search_node_count,
-        leaf_node_count: search.eval_search_leaf_node_count
+        best_move: 1 << progress.best_move.unwrap(),
+        eval: progress.score,
+        node_count: search.node_count(),
+        leaf_node_count: search.eval_search_leaf_node_count + search.perfect_search_leaf_node_count
     })
-}
\ No newline at end of file
+}
+
+/// 全ての合法手について、着手後の局面を`eval_solver`と同じ深さで探索し、評価値を求める。
+///
+/// 最善手以外の手も正確な評価値が必要な場合(ランダムな着手の選択など)に使用する。
+///
+/// # 戻り値
+/// * 各合法手の`SolverResult`を、評価値の高い順に並べたもの
+///   * `best_move`はその合法手、`node_count`と`leaf_node_count`はその合法手の探索に要したノード数
+/// * 合法手が存在しない場合は、`SolverErr::NoMove`
+pub fn eval_all_moves(board: &Board, lv: i32, selectivity_lv: i32, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<Vec<SolverResult>, SolverErr>
+{
+    let legal_moves = board.put_able();
+    if legal_moves == 0 {
+        return Err(SolverErr::NoMove)
+    }
+
+    let mut search = Search::new(board, selectivity_lv, t_table, evaluator);
+    let mut results = Vec::new();
+    for put_board in get_put_boards(board, legal_moves).iter() {
+        let node_count = search.eval_search_node_count;
+        let leaf_node_count = search.eval_search_leaf_node_count;
+        let eval = -pvs_eval(&put_board.board, -SCORE_INF, SCORE_INF, lv - 1, &mut search);
+        results.push(SolverResult {
+            best_move: 1 << put_board.put_place,
+            eval,
+            node_count: search.eval_search_node_count - node_count,
+            leaf_node_count: search.eval_search_leaf_node_count - leaf_node_count
+        });
+    }
+
+    results.sort_by_key(|r| std::cmp::Reverse(r.eval));
+    Ok(results)
+}
+
+/// 定石に盤面がある場合は定石の最善手を返し、ない場合は`eval_solver`で探索する。
+///
+/// # 引数
+/// * `book` - 参照する定石
+/// * その他の引数は`eval_solver`と同じ
+///
+/// # 戻り値
+/// * 定石の手を返した場合、`node_count`と`leaf_node_count`は0となる。
+pub fn eval_solver_with_book(board: &Board, book: &Book, lv: i32, selectivity_lv: i32, print_log: bool, t_table: &mut TranspositionTable, evaluator : &mut Evaluator) -> Result<SolverResult, SolverErr>
+{
+    if let Some(book_move) = book.probe(board).and_then(|moves| moves.first().copied()) {
+        if board.put_able() & (1 << book_move.put_place) != 0 {
+            if print_log {
+                println!("book move: {}, score: {}", Board::move_bit_to_str(1 << book_move.put_place).unwrap(), book_move.value);
+            }
+            return Ok(SolverResult {
+                best_move: 1 << book_move.put_place,
+                eval: book_move.value as i32,
+                node_count: 0,
+                leaf_node_count: 0
+            });
+        }
+    }
+
+    eval_solver(board, lv, selectivity_lv, print_log, t_table, evaluator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mpc::NO_MPC;
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    /// 枝刈りを行わない、素朴なNegaMax法による完全読み
+    fn brute_force(board: &Board) -> i32 {
+        let mut legal_moves = board.put_able();
+        if legal_moves == 0 {
+            if board.opponent_put_able() == 0 {
+                return solve_score(board);
+            }
+            let mut passed_board = board.clone();
+            passed_board.pass();
+            return -brute_force(&passed_board);
+        }
+        let mut best_score = -SCORE_INF;
+        while legal_moves != 0 {
+            let put_place = legal_moves & legal_moves.wrapping_neg();
+            legal_moves &= legal_moves - 1;
+            let mut current_board = board.clone();
+            current_board.put_piece_fast(put_place);
+            best_score = best_score.max(-brute_force(&current_board));
+        }
+        best_score
+    }
+
+    #[test]
+    fn perfect_solver_matches_brute_force() {
+        let mut rng = XorShiftRng::seed_from_u64(0);
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+
+        for n_empties in [8, 10] {
+            for _ in 0..12 {
+                let board = random_board(&mut rng, n_empties).unwrap();
+                let expected = brute_force(&board);
+                let Ok(result) = perfect_solver(&board, false, NO_MPC, &mut t_table, &mut evaluator) else {
+                    panic!("perfect_solver returned an error");
+                };
+                assert_eq!(result.eval, expected);
+
+                // 最善手を打った後の盤面のスコアも、最善の値となる。
+                let mut next_board = board.clone();
+                next_board.put_piece_fast(result.best_move);
+                assert_eq!(-brute_force(&next_board), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn perfect_solver_with_etc_matches_simple_search() {
+        let mut rng = XorShiftRng::seed_from_u64(4);
+        let mut evaluator = Evaluator::new();
+
+        // 置換表を共有して連続で解き、置換表を使用しない探索と一致することを確かめる
+        let mut t_table = TranspositionTable::new();
+        for _ in 0..4 {
+            let board = random_board(&mut rng, 14).unwrap();
+            let Ok(result) = perfect_solver(&board, false, NO_MPC, &mut t_table, &mut evaluator) else {
+                panic!("perfect_solver returned an error");
+            };
+            let mut simple_t_table = TranspositionTable::new();
+            let mut search = Search::new(&board, NO_MPC, &mut simple_t_table, &mut evaluator);
+            assert_eq!(result.eval, pvs_perfect_simple(&board, -SCORE_INF, SCORE_INF, &mut search));
+        }
+    }
+
+    #[test]
+    fn perfect_solver_score_does_not_depend_on_config() {
+        let mut rng = XorShiftRng::seed_from_u64(7);
+        let mut evaluator = Evaluator::new();
+
+        // 閾値を変えても、探索の速さのみが変わり、完全読みの結果は変わらない
+        let config = SearchConfig {
+            switch_empties_simple_pvs: 10,
+            switch_empties_simple_nws: 6,
+            switch_empties_last_moves: 2,
+            switch_empties_nega_alpha: 7,
+            switch_empties_move_order: 9,
+            etc_min_empties: 9,
+            use_move_history: false,
+            ..SearchConfig::default()
+        };
+        for _ in 0..6 {
+            let board = random_board(&mut rng, 12).unwrap();
+            let Ok(result) = perfect_solver_with_observer(&board, NO_MPC, &mut TranspositionTable::new(), &mut evaluator, &config, &mut NullObserver) else {
+                panic!("perfect_solver returned an error");
+            };
+            let Ok(expected) = perfect_solver(&board, false, NO_MPC, &mut TranspositionTable::new(), &mut evaluator) else {
+                panic!("perfect_solver returned an error");
+            };
+            assert_eq!(result.eval, expected.eval);
+        }
+    }
+
+    #[test]
+    fn last_moves_solver_matches_brute_force() {
+        let mut rng = XorShiftRng::seed_from_u64(6);
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+
+        for n_empties in 1..=4 {
+            for _ in 0..200 {
+                let mut board = random_board(&mut rng, n_empties).unwrap();
+                // パスした盤面(手番側が置けない盤面)も調べる
+                if rng.gen_bool(0.5) {
+                    board.pass();
+                }
+                let expected = brute_force(&board);
+
+                let mut search = Search::new(&board, NO_MPC, &mut t_table, &mut evaluator);
+                assert_eq!(solve_last_moves(&board, -SCORE_INF, SCORE_INF, &mut search), expected);
+
+                // 探索窓の外の値は、fail-softの上限・下限となる
+                let alpha = rng.gen_range(-64..64);
+                let beta = rng.gen_range(alpha + 1..=64);
+                let score = solve_last_moves(&board, alpha, beta, &mut search);
+                if expected <= alpha {
+                    assert!(expected <= score && score <= alpha);
+                } else if expected >= beta {
+                    assert!(beta <= score && score <= expected);
+                } else {
+                    assert_eq!(score, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exact_score_respects_stable_discs() {
+        let mut rng = XorShiftRng::seed_from_u64(5);
+
+        for _ in 0..24 {
+            let board = random_board(&mut rng, 10).unwrap();
+            let score = brute_force(&board);
+            assert!(score <= 64 - 2 * board.opponent_stable_discs().count_ones() as i32);
+            assert!(score >= 2 * board.stable_discs().count_ones() as i32 - 64);
+        }
+    }
+
+    #[test]
+    fn aspiration_root_recovers_from_wrong_estimate() {
+        let mut rng = XorShiftRng::seed_from_u64(3);
+        let mut evaluator = Evaluator::new();
+
+        for _ in 0..8 {
+            let board = random_board(&mut rng, 9).unwrap();
+            let expected = brute_force(&board);
+            let put_boards = get_put_boards(&board, board.put_able());
+
+            // 推定値が大きく外れていても、窓を広げて再探索し、正しい評価値になる
+            for estimate in [None, Some(expected), Some(-SCORE_INF), Some(SCORE_INF), Some(expected + 13)] {
+                let mut t_table = TranspositionTable::new();
+                let mut search = Search::new(&board, NO_MPC, &mut t_table, &mut evaluator);
+                let Some((_, score)) = aspiration_root(&put_boards, estimate, &mut search, pvs_perfect, nws_perfect) else {
+                    panic!("aspiration_root stopped without a node limit");
+                };
+                assert_eq!(score, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn selectivity_ladder_solver_ends_with_exact_score() {
+        let mut rng = XorShiftRng::seed_from_u64(8);
+        let mut evaluator = Evaluator::new();
+
+        for n_empties in [9, 12] {
+            let board = random_board(&mut rng, n_empties).unwrap();
+            let expected = brute_force(&board);
+
+            // 各段階の (選択的探索のレベル, 確からしさ)
+            let mut stages = Vec::new();
+            let mut observer = |event: &SearchEvent| {
+                if let SearchEvent::IterationFinished(progress) = event {
+                    if progress.depth == n_empties {
+                        stages.push((progress.selectivity_lv, progress.confidence));
+                    }
+                }
+            };
+            let Ok(result) = selectivity_ladder_solver_with_observer(&board, SELECTIVITY_LV_MAX, &mut TranspositionTable::new(), &mut evaluator, &SearchConfig::default(), &mut observer) else {
+                panic!("selectivity_ladder_solver returned an error");
+            };
+            assert_eq!(result.eval, expected);
+            assert_eq!(stages, vec![(6, 68), (5, 77), (4, 85), (3, 95), (2, 98), (1, 99), (0, 100)]);
+
+            let mut next_board = board.clone();
+            next_board.put_piece_fast(result.best_move);
+            assert_eq!(-brute_force(&next_board), expected);
+        }
+    }
+
+    #[test]
+    fn winning_solver_matches_brute_force() {
+        let mut rng = XorShiftRng::seed_from_u64(1);
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+
+        for _ in 0..12 {
+            let board = random_board(&mut rng, 10).unwrap();
+            let expected = brute_force(&board).signum();
+            let Ok(result) = winning_solver(&board, false, &mut t_table, &mut evaluator) else {
+                panic!("winning_solver returned an error");
+            };
+            assert_eq!(result.eval, expected);
+
+            let mut next_board = board.clone();
+            next_board.put_piece_fast(result.best_move);
+            assert_eq!((-brute_force(&next_board)).signum(), expected);
+        }
+    }
+
+    #[test]
+    fn wld_all_moves_classifies_every_move() {
+        let mut rng = XorShiftRng::seed_from_u64(9);
+        let mut t_table = TranspositionTable::new();
+        let mut evaluator = Evaluator::new();
+
+        for n_empties in [8, 11] {
+            for _ in 0..6 {
+                let board = random_board(&mut rng, n_empties).unwrap();
+                let Ok(results) = wld_all_moves(&board, NO_MPC, false, &mut t_table, &mut evaluator) else {
+                    panic!("wld_all_moves returned an error");
+                };
+                assert_eq!(results.len(), board.put_able().count_ones() as usize);
+                assert!(results.windows(2).all(|r| r[0].wld >= r[1].wld));
+                for result in results.iter() {
+                    let mut next_board = board.clone();
+                    next_board.put_piece_fast(1 << result.put_place);
+                    assert_eq!(result.wld.score(), (-brute_force(&next_board)).signum());
+                }
+                // 最善の分類は、winning_solverの評価値と一致する
+                let Ok(winning) = winning_solver(&board, false, &mut t_table, &mut evaluator) else {
+                    panic!("winning_solver returned an error");
+                };
+                assert_eq!(results[0].wld.score(), winning.eval);
+            }
+        }
+
+        // MPCを使用しても、全ての合法手を分類する (誤りうるため、分類の正しさは確かめない)
+        let board = random_board(&mut rng, 14).unwrap();
+        if let Ok(results) = wld_all_moves(&board, 3, false, &mut t_table, &mut evaluator) {
+            assert_eq!(results.len(), board.put_able().count_ones() as usize);
+        }
+    }
+
+    #[test]
+    fn node_limited_solver_is_reproducible() {
+        let mut rng = XorShiftRng::seed_from_u64(2);
+        let mut evaluator = Evaluator::new();
+
+        for _ in 0..4 {
+            // 上限がなければ、空きマス数以上の深さまで探索し、完全読みと一致する
+            let board = random_board(&mut rng, 9).unwrap();
+            let Ok(result) = node_limited_solver(&board, 10, NO_MPC, u64::MAX, false, &mut TranspositionTable::new(), &mut evaluator) else {
+                panic!("node_limited_solver returned an error");
+            };
+            assert_eq!(result.eval, brute_force(&board));
+
+            // 同じ上限であれば、同じ結果になる
+            let board = random_board(&mut rng, 30).unwrap();
+            let search = |evaluator: &mut Evaluator| {
+                let Ok(result) = node_limited_solver(&board, 60, NO_MPC, 20_000, false, &mut TranspositionTable::new(), evaluator) else {
+                    panic!("node_limited_solver returned an error");
+                };
+                (result.best_move, result.eval, result.node_count)
+            };
+            let first = search(&mut evaluator);
+            assert_eq!(search(&mut evaluator), first);
+            assert_ne!(board.put_able() & first.0, 0);
+        }
+    }
+}
@@ -1,10 +1,10 @@
 use crate::board::*;
-use rand::{Rng};
 
 #[derive(Clone)]
 pub struct TableData {
     exists: bool,
-    pub board: Board,
+    /// 盤面のZobrist hash (インデックスの衝突を検出するための検証キー)
+    pub key: u64,
     pub max: i8,
     pub min: i8,
     pub lv: u8,
@@ -16,7 +16,7 @@ impl TableData {
     fn make_blank() -> Self{
         Self {
             exists: false,
-            board: Board {bit_board: [0, 0],next_turn: 0},
+            key: 0,
             max: 0,
             min: 0,
             lv: 0,
@@ -29,15 +29,12 @@ impl TableData {
 const TABLE_SIZE: usize = 1 << 20;
 pub struct TranspositionTable {
     table: Vec::<TableData>,
-    rand_table: [u32; 1<<16]
 }
 
 impl Default for TranspositionTable {
     fn default() -> Self {
-        let rand_table: [u32; 1<<16] = Self::gen_rand_table();
         Self {
             table: vec![TableData::make_blank(); TABLE_SIZE],
-            rand_table
         }
     }
 }
@@ -46,32 +43,13 @@ impl TranspositionTable {
     pub fn new() -> Self{
         Self::default()
     }
-    fn gen_rand_table() -> [u32; 1<<16] {
-        let mut rng = rand::thread_rng();
-        let mut table = [0; 1<<16];
-    
-        for ti in table.iter_mut() {
-            *ti = rng.gen_range(0..TABLE_SIZE as u32);
-        }
-    
-        table
-    }
 
     #[inline(always)]
     pub fn hash_board(&self, board: &Board) -> usize{
-        let player_board_bit = board.bit_board[board.next_turn];
-        let opponent_board_bit = board.bit_board[board.next_turn ^ 1];
+        #[cfg(debug_assertions)]
+        assert_eq!(board.hash(), board.calc_hash(), "Board::hash is out of date");
 
-        (
-            self.rand_table[(player_board_bit & 0xFFFF) as usize] ^
-            self.rand_table[((player_board_bit >> 16) & 0xFFFF) as usize] ^
-            self.rand_table[((player_board_bit >> 32) & 0xFFFF) as usize] ^
-            self.rand_table[((player_board_bit >> 48) & 0xFFFF) as usize] ^
-            self.rand_table[((opponent_board_bit >> 48) & 0xFFFF) as usize] ^
-            self.rand_table[((opponent_board_bit >> 32) & 0xFFFF) as usize] ^
-            self.rand_table[((opponent_board_bit >> 16) & 0xFFFF) as usize] ^
-            self.rand_table[(opponent_board_bit & 0xFFFF) as usize]
-        ) as usize
+        board.hash() as usize & (TABLE_SIZE - 1)
     }
 
     #[inline(always)]
@@ -81,13 +59,13 @@ impl TranspositionTable {
     {
         const MAX:i32 = i8::MAX as i32;
         const MIN:i32 = i8::MIN as i32;
-        assert!(MIN <= min && min <= max && max <= MAX, 
+        assert!(MIN <= min && min <= max && max <= MAX,
             " in function t_table::add() , min: {min}, max: {min}, Lv: {lv}, best move: {best_move}");
     }
         let index = self.hash_board(board);
         self.table[index] = TableData {
             exists: true,
-            board: board.clone(),
+            key: board.hash(),
             max: max as i8,
             min: min as i8,
             lv: lv as u8,
@@ -102,13 +80,11 @@ impl TranspositionTable {
         let x = &self.table[index];
         if !x.exists {return None;}
 
-        if x.board.bit_board[Board::BLACK] == board.bit_board[Board::BLACK] &&
-           x.board.bit_board[Board::WHITE] == board.bit_board[Board::WHITE] &&
-           x.board.next_turn == board.next_turn {
+        if x.key == board.hash() {
             Some(x)
         } else {
             None
         }
     }
 
-}
\ No newline at end of file
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::time;
+
+use rand::SeedableRng;
+use rand_xorshift::XorShiftRng;
+
+use crate::board::*;
+use crate::eval::*;
+use crate::ffo_test::*;
+use crate::mpc::{NO_MPC, SELECTIVITY};
+use crate::search_config::*;
+use crate::search_observer::NullObserver;
+use crate::solver::*;
+use crate::t_table::*;
+
+/// パラメータの探索方法
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TuneMethod {
+    /// 全ての組み合わせを試す
+    Grid,
+    /// 1つずつパラメータを動かし、改善が止まるまで繰り返す (座標降下法)
+    CoordinateDescent,
+}
+
+/// 最小化する指標
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TuneMetric {
+    /// 探索ノード数の合計 (実行環境に依存しない)
+    Nodes,
+    /// 実行時間の合計
+    Time,
+}
+
+/// 調整するパラメータと、試す値
+#[derive(Clone, Debug)]
+pub struct TuneParam {
+    /// `SearchConfig::param_mut`で指定するパラメータ名
+    pub name: String,
+    pub values: Vec<i32>,
+}
+
+/// パラメータの自動調整の設定
+pub struct TuneConfig {
+    pub params: Vec<TuneParam>,
+    pub method: TuneMethod,
+    pub metric: TuneMetric,
+    pub selectivity_lv: i32,
+    /// 座標降下法で、全てのパラメータを動かす回数の上限
+    pub max_rounds: usize,
+}
+
+impl Default for TuneConfig {
+    fn default() -> Self {
+        Self {
+            params: TUNABLE_PARAMS.iter()
+                .map(|&(name, min, max)| TuneParam { name: name.to_string(), values: (min..=max).collect() })
+                .collect(),
+            method: TuneMethod::CoordinateDescent,
+            metric: TuneMetric::Nodes,
+            selectivity_lv: NO_MPC,
+            max_rounds: 3,
+        }
+    }
+}
+
+/// 1つのパラメータで、問題集を解いた結果
+#[derive(Clone, Debug)]
+pub struct TuneResult {
+    pub config: SearchConfig,
+    pub node_count: u64,
+    pub time: f64,
+    pub scores: Vec<i32>,
+}
+
+impl TuneResult {
+    fn cost(&self, metric: TuneMetric) -> f64 {
+        match metric {
+            TuneMetric::Nodes => self.node_count as f64,
+            TuneMetric::Time => self.time,
+        }
+    }
+}
+
+/// ランダムに着手し、空きマスが`n_empties`の、手番側に合法手がある局面を`n_positions`個生成する。(`random_board`を参照)
+///
+/// 同じ`seed`からは、同じ局面が生成される。
+pub fn random_positions(n_positions: usize, n_empties: i32, seed: u64) -> Result<Vec<Board>, String> {
+    if !(1..=60).contains(&n_empties) {
+        return Err("empties must be 1 ~ 60".to_string());
+    }
+    let mut rng = XorShiftRng::seed_from_u64(seed);
+    (0..n_positions)
+        .map(|_| random_board(&mut rng, n_empties).ok_or_else(|| format!("failed to generate a position with {} empties", n_empties)))
+        .collect()
+}
+
+/// `config`で、`boards`の全ての局面を`perfect_solver`で解く。
+///
+/// 局面ごとに置換表を初期化するため、解く順序やそれまでの探索に結果が依存しない。
+pub fn run_suite(boards: &[Board], selectivity_lv: i32, config: &SearchConfig, evaluator: &mut Evaluator) -> TuneResult {
+    let mut result = TuneResult { config: config.clone(), node_count: 0, time: 0.0, scores: Vec::with_capacity(boards.len()) };
+    for board in boards.iter() {
+        let mut t_table = TranspositionTable::new();
+        let now = time::Instant::now();
+        let Ok(solver_result) = perfect_solver_with_observer(board, selectivity_lv, &mut t_table, evaluator, config, &mut NullObserver) else {
+            panic!("no legal moves in a tuning position");
+        };
+        result.time += now.elapsed().as_secs_f64();
+        result.node_count += solver_result.node_count;
+        result.scores.push(solver_result.eval);
+    }
+    result
+}
+
+/// パラメータの自動調整
+///
+/// 問題集`boards`を解くノード数(または時間)が最小となるよう、`base`のパラメータのうち`tune_config.params`を調整する。
+/// 各局面のスコアが`base`で解いた結果と異なるパラメータは、採用しない。
+/// 範囲外の値などで`SearchConfig::validate`に失敗するパラメータは、試さない。
+///
+/// # 戻り値
+/// * `(baseの結果, 最良の結果)`
+pub fn tune(boards: &[Board], base: &SearchConfig, tune_config: &TuneConfig, evaluator: &mut Evaluator) -> Result<(TuneResult, TuneResult), String> {
+    base.validate()?;
+    for param in tune_config.params.iter() {
+        if base.clone().param_mut(&param.name).is_none() {
+            return Err(format!("unknown parameter: {}", param.name));
+        }
+        if param.values.is_empty() {
+            return Err(format!("no values for {}", param.name));
+        }
+    }
+
+    let metric = tune_config.metric;
+    let base_result = run_suite(boards, tune_config.selectivity_lv, base, evaluator);
+    print_result("base", &base_result);
+
+    let base_values: Vec<i32> = tune_config.params.iter()
+        .map(|param| *base.clone().param_mut(&param.name).unwrap())
+        .collect();
+
+    // 試したパラメータの値の組と、その結果 (スコアが異なるか、無効な場合は`None`)
+    let mut results: HashMap<Vec<i32>, Option<TuneResult>> = HashMap::new();
+    results.insert(base_values.clone(), Some(base_result.clone()));
+    let mut try_values = |values: &[i32], evaluator: &mut Evaluator| -> Option<TuneResult> {
+        if let Some(result) = results.get(values) {
+            return result.clone();
+        }
+        let mut config = base.clone();
+        for (param, &value) in tune_config.params.iter().zip(values) {
+            *config.param_mut(&param.name).unwrap() = value;
+        }
+        let label = tune_config.params.iter().zip(values)
+            .map(|(param, value)| format!("{}={}", param.name, value))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let result = match config.validate() {
+            Err(e) => {
+                println!("{}: skipped ({})", label, e);
+                None
+            },
+            Ok(()) => {
+                let result = run_suite(boards, tune_config.selectivity_lv, &config, evaluator);
+                print_result(&label, &result);
+                if result.scores == base_result.scores {
+                    Some(result)
+                } else {
+                    println!("    rejected: scores differ from the base configuration");
+                    None
+                }
+            }
+        };
+        results.insert(values.to_vec(), result.clone());
+        result
+    };
+
+    let mut best = base_result.clone();
+
+    match tune_config.method {
+        TuneMethod::Grid => {
+            let mut indices = vec![0; tune_config.params.len()];
+            'grid: loop {
+                let values: Vec<i32> = indices.iter().zip(tune_config.params.iter()).map(|(&i, param)| param.values[i]).collect();
+                if let Some(result) = try_values(&values, evaluator) {
+                    if result.cost(metric) < best.cost(metric) {
+                        best = result;
+                    }
+                }
+                // 次の組み合わせ
+                for (index, param) in indices.iter_mut().zip(tune_config.params.iter()) {
+                    *index += 1;
+                    if *index < param.values.len() {
+                        continue 'grid;
+                    }
+                    *index = 0;
+                }
+                break;
+            }
+        },
+        TuneMethod::CoordinateDescent => {
+            let mut best_values = base_values;
+            for round in 0..tune_config.max_rounds {
+                let mut improved = false;
+                for (i, param) in tune_config.params.iter().enumerate() {
+                    for &value in param.values.iter() {
+                        let mut values = best_values.clone();
+                        values[i] = value;
+                        let Some(result) = try_values(&values, evaluator) else {continue};
+                        if result.cost(metric) < best.cost(metric) {
+                            best = result;
+                            best_values = values;
+                            improved = true;
+                        }
+                    }
+                }
+                println!("round {}: best nodes {}, time {:.3}s", round + 1, best.node_count, best.time);
+                if !improved {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok((base_result, best))
+}
+
+fn print_result(label: &str, result: &TuneResult) {
+    println!("{}: nodes {}, time {:.3}s", label, result.node_count, result.time);
+}
+
+/// 調整するパラメータを解釈する。 (例: `"etc_min_empties=8..16"`, `"switch_empties_move_order=12,14,16"`)
+pub fn parse_tune_param(s: &str) -> Result<TuneParam, String> {
+    let (name, values) = s.split_once('=').ok_or(format!("invalid parameter: {}", s))?;
+    let parse = |x: &str| x.trim().parse::<i32>().map_err(|_| format!("invalid value: {}", x));
+    let values = match values.split_once("..") {
+        Some((min, max)) => (parse(min)?..=parse(max)?).collect(),
+        None => values.split(',').map(parse).collect::<Result<Vec<_>, _>>()?
+    };
+    Ok(TuneParam { name: name.to_string(), values })
+}
+
+/// コマンドラインから、探索のパラメータを自動調整する。
+///
+/// ```text
+/// deft_reversi_engine tune [--positions 40-49 | --random 20 --empties 18 --seed 0] [--selectivity 0]
+///                          [--config base.json] [--param etc_min_empties=8..16 ...]
+///                          [--method descent|grid] [--rounds 3] [--metric nodes|time] [--output best.json]
+/// ```
+///
+/// `--positions`はFFO endgame testの問題番号、`--random`は生成する局面の数を指定する。
+/// `--param`を指定しない場合は、`TUNABLE_PARAMS`の全てのパラメータを調整する。
+pub fn tune_cli(args: &[String]) -> Result<(), String> {
+    let mut tune_config = TuneConfig::default();
+    let mut params = Vec::new();
+    let mut positions = None;
+    let mut n_random = 0;
+    let mut n_empties = 18;
+    let mut seed = 0;
+    let mut base = SearchConfig::default();
+    let mut output = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--positions"   => positions = Some(parse_positions(value()?)?),
+            "--random"      => n_random = value()?.parse().map_err(|_| "invalid number of positions")?,
+            "--empties"     => n_empties = value()?.parse().map_err(|_| "invalid empties")?,
+            "--seed"        => seed = value()?.parse().map_err(|_| "invalid seed")?,
+            "--selectivity" => tune_config.selectivity_lv = value()?.parse().map_err(|_| "invalid selectivity")?,
+            "--config"      => {
+                let path = value()?;
+                base = SearchConfig::read_file(path).map_err(|e| format!("{}: {}", path, e))?;
+            },
+            "--param"       => params.push(parse_tune_param(value()?)?),
+            "--method"      => tune_config.method = match value()?.as_str() {
+                "descent" => TuneMethod::CoordinateDescent,
+                "grid"    => TuneMethod::Grid,
+                m => return Err(format!("unknown method: {}", m))
+            },
+            "--rounds"      => tune_config.max_rounds = value()?.parse().map_err(|_| "invalid rounds")?,
+            "--metric"      => tune_config.metric = match value()?.as_str() {
+                "nodes" => TuneMetric::Nodes,
+                "time"  => TuneMetric::Time,
+                m => return Err(format!("unknown metric: {}", m))
+            },
+            "--output"      => output = Some(value()?.clone()),
+            _ => return Err(format!("unknown option: {}", arg))
+        }
+    }
+    if !(0..SELECTIVITY.len() as i32).contains(&tune_config.selectivity_lv) {
+        return Err(format!("selectivity must be 0 ~ {}", SELECTIVITY.len() - 1));
+    }
+    if !params.is_empty() {
+        tune_config.params = params;
+    }
+
+    let mut boards = Vec::new();
+    for &no in positions.iter().flatten() {
+        boards.push(read_ffo_position(no).map_err(|e| format!("#{}: {}", no, e))?);
+    }
+    if !(1..=59).contains(&n_empties) {
+        return Err("empties must be 1 ~ 59".to_string());
+    }
+    boards.extend(random_positions(n_random, n_empties, seed)?);
+    if boards.is_empty() {
+        return Err("no positions (use --positions or --random)".to_string());
+    }
+
+    let mut evaluator = Evaluator::read_file().map_err(|e| e.to_string())?;
+    let (base_result, best) = tune(&boards, &base, &tune_config, &mut evaluator)?;
+
+    println!();
+    println!("base: nodes {}, time {:.3}s", base_result.node_count, base_result.time);
+    println!("best: nodes {} ({:+.1} %), time {:.3}s ({:+.1} %)",
+        best.node_count, (best.node_count as f64 / base_result.node_count as f64 - 1.0) * 100.0,
+        best.time, (best.time / base_result.time - 1.0) * 100.0);
+    for param in tune_config.params.iter() {
+        let mut best_config = best.config.clone();
+        println!("    {} = {}", param.name, best_config.param_mut(&param.name).unwrap());
+    }
+
+    if let Some(path) = output {
+        best.config.write_file(&path).map_err(|e| format!("{}: {}", path, e))?;
+        println!("saved: {}", path);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tuning_keeps_scores_and_does_not_increase_nodes() {
+        let boards = random_positions(3, 10, 0).unwrap();
+        assert!(boards.iter().all(|b| b.empties_count() == 10 && b.put_able() != 0));
+        let mut evaluator = Evaluator::new();
+
+        for method in [TuneMethod::Grid, TuneMethod::CoordinateDescent] {
+            let tune_config = TuneConfig {
+                params: vec![
+                    parse_tune_param("switch_empties_nega_alpha=5..7").unwrap(),
+                    parse_tune_param("switch_empties_last_moves=2,4,5").unwrap(),
+                ],
+                method,
+                ..TuneConfig::default()
+            };
+            let Ok((base, best)) = tune(&boards, &SearchConfig::default(), &tune_config, &mut evaluator) else {
+                panic!("tune returned an error");
+            };
+            assert_eq!(best.scores, base.scores);
+            assert!(best.node_count <= base.node_count);
+            assert_eq!(run_suite(&boards, NO_MPC, &best.config, &mut evaluator).node_count, best.node_count);
+        }
+
+        let tune_config = TuneConfig { params: vec![parse_tune_param("unknown=1..2").unwrap()], ..TuneConfig::default() };
+        assert!(tune(&boards, &SearchConfig::default(), &tune_config, &mut evaluator).is_err());
+    }
+}
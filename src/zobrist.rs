@@ -0,0 +1,60 @@
+/// Zobrist hashで使用する乱数表(`ZOBRIST`)の生成に用いるシード値
+///
+/// 乱数表はコンパイル時に生成されるため、同じシード値であれば、
+/// 実行ごと・マシンごとに同じハッシュ値となる。
+/// シード値は実行時には変更できない。変更する場合は、この定数を書き換えて再コンパイルする。
+pub const ZOBRIST_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+pub struct ZobristKeys {
+    /// `piece[color][square]` : `color`の石が`square`にあることを表す乱数
+    pub piece: [[u64; 64]; 2],
+    /// `flip[square]` : `square`の石が裏返ったことを表す乱数 (`piece[0][square] ^ piece[1][square]`)
+    pub flip: [u64; 64],
+    /// 白番であることを表す乱数
+    pub side: u64
+}
+
+/// SplitMix64
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+impl ZobristKeys {
+    /// シード値から乱数表を生成する。
+    pub const fn new(seed: u64) -> Self {
+        let mut piece = [[0u64; 64]; 2];
+        let mut flip = [0u64; 64];
+        let mut state = seed;
+
+        let mut color = 0;
+        while color < 2 {
+            let mut square = 0;
+            while square < 64 {
+                let (s, r) = splitmix64(state);
+                state = s;
+                piece[color][square] = r;
+                square += 1;
+            }
+            color += 1;
+        }
+
+        let mut square = 0;
+        while square < 64 {
+            flip[square] = piece[0][square] ^ piece[1][square];
+            square += 1;
+        }
+
+        let (_, side) = splitmix64(state);
+
+        Self { piece, flip, side }
+    }
+}
+
+/// `Board`のハッシュ値の計算に使用する乱数表 (シード値は`ZOBRIST_SEED`)
+///
+/// `ZobristKeys::new`で他のシード値の乱数表も生成できるが、`Board`は常にこの乱数表を使用する。
+pub static ZOBRIST: ZobristKeys = ZobristKeys::new(ZOBRIST_SEED);
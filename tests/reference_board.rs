@@ -0,0 +1,178 @@
+//! ビットボードの実装(`Board`)を、素朴な配列による実装と比較するテスト
+//!
+//! ランダムに対局を進め、各手番で、合法手、裏返る石、パス・終局の判定、最終スコアが一致することを確認する。
+
+use deft_reversi_engine::*;
+use deft_reversi_engine::perfect_search::solve_score;
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+
+const EMPTY: i8 = -1;
+
+const DIRECTIONS: [(i32, i32); 8] = [
+    (-1, -1), (-1, 0), (-1, 1),
+    ( 0, -1),          ( 0, 1),
+    ( 1, -1), ( 1, 0), ( 1, 1),
+];
+
+/// 配列で盤面を表す、遅いが明らかに正しいオセロの実装
+#[derive(Clone)]
+struct ReferenceBoard {
+    cells: [i8; 64],
+    next_turn: i8
+}
+
+impl ReferenceBoard {
+    fn new() -> Self {
+        let mut cells = [EMPTY; 64];
+        cells[(3 * 8 + 3) as usize] = Board::WHITE as i8; // d4
+        cells[(3 * 8 + 4) as usize] = Board::BLACK as i8; // e4
+        cells[(4 * 8 + 3) as usize] = Board::BLACK as i8; // d5
+        cells[(4 * 8 + 4) as usize] = Board::WHITE as i8; // e5
+        Self { cells, next_turn: Board::BLACK as i8 }
+    }
+
+    fn get(&self, y: i32, x: i32) -> i8 {
+        self.cells[(y * 8 + x) as usize]
+    }
+
+    /// 着手した場合に裏返る石の位置
+    fn flips(&self, y: i32, x: i32, color: i8) -> Vec<(i32, i32)> {
+        let mut result = Vec::new();
+        if self.get(y, x) != EMPTY {
+            return result;
+        }
+        for (dy, dx) in DIRECTIONS {
+            let mut line = Vec::new();
+            let (mut cy, mut cx) = (y + dy, x + dx);
+            while (0..8).contains(&cy) && (0..8).contains(&cx) && self.get(cy, cx) == 1 - color {
+                line.push((cy, cx));
+                cy += dy;
+                cx += dx;
+            }
+            if !line.is_empty() && (0..8).contains(&cy) && (0..8).contains(&cx) && self.get(cy, cx) == color {
+                result.append(&mut line);
+            }
+        }
+        result
+    }
+
+    fn legal_moves(&self, color: i8) -> u64 {
+        let mut moves = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                if !self.flips(y, x, color).is_empty() {
+                    moves |= 1 << (y * 8 + x);
+                }
+            }
+        }
+        moves
+    }
+
+    fn put(&mut self, y: i32, x: i32) {
+        for (fy, fx) in self.flips(y, x, self.next_turn) {
+            self.cells[(fy * 8 + fx) as usize] = self.next_turn;
+        }
+        self.cells[(y * 8 + x) as usize] = self.next_turn;
+        self.next_turn = 1 - self.next_turn;
+    }
+
+    fn count(&self, color: i8) -> i32 {
+        self.cells.iter().filter(|&&c| c == color).count() as i32
+    }
+
+    /// 手番のプレイヤーから見たスコア (空きマスは勝っている側に加算する)
+    fn score(&self) -> i32 {
+        let p = self.count(self.next_turn);
+        let o = self.count(1 - self.next_turn);
+        let empties = 64 - p - o;
+        if p > o {
+            p - o + empties
+        } else if p < o {
+            p - o - empties
+        } else {
+            0
+        }
+    }
+
+    fn to_bit_board(&self) -> [u64; 2] {
+        let mut bit_board = [0u64; 2];
+        for (i, &c) in self.cells.iter().enumerate() {
+            if c != EMPTY {
+                bit_board[c as usize] |= 1 << i;
+            }
+        }
+        bit_board
+    }
+}
+
+fn assert_same(board: &Board, reference: &ReferenceBoard, ply: usize) {
+    assert_eq!(board.bit_board, reference.to_bit_board(), "ply: {}", ply);
+    assert_eq!(board.next_turn as i8, reference.next_turn, "ply: {}", ply);
+    assert_eq!(board.hash(), board.calc_hash(), "ply: {}", ply);
+}
+
+/// ランダムな対局を1局行い、各手番で`Board`と`ReferenceBoard`を比較する。
+fn play_random_game(rng: &mut XorShiftRng) {
+    let mut board = Board::new();
+    let mut reference = ReferenceBoard::new();
+
+    for ply in 0.. {
+        assert_same(&board, &reference, ply);
+        assert_eq!(solve_score(&board), reference.score(), "ply: {}", ply);
+
+        // 合法手
+        let legal_moves = board.put_able();
+        assert_eq!(legal_moves, reference.legal_moves(reference.next_turn), "ply: {}", ply);
+        assert_eq!(board.opponent_put_able(), reference.legal_moves(1 - reference.next_turn), "ply: {}", ply);
+
+        // 全ての合法手について、裏返る石
+        let mut moves = legal_moves;
+        while moves != 0 {
+            let put_place = moves.trailing_zeros() as i32;
+            moves &= moves - 1;
+            let expected = reference.flips(put_place / 8, put_place % 8, reference.next_turn)
+                .iter()
+                .fold(0u64, |acc, &(y, x)| acc | 1 << (y * 8 + x));
+            assert_eq!(board.flip_bit(1 << put_place), expected, "ply: {}, move: {}", ply, put_place);
+        }
+
+        // パス・終局
+        if legal_moves == 0 {
+            if board.opponent_put_able() == 0 {
+                assert!(reference.legal_moves(1 - reference.next_turn) == 0, "ply: {}", ply);
+                return;
+            }
+            board.pass();
+            reference.next_turn = 1 - reference.next_turn;
+            continue;
+        }
+
+        // ランダムに着手
+        let n = rng.gen_range(0..legal_moves.count_ones());
+        let mut moves = legal_moves;
+        for _ in 0..n {
+            moves &= moves - 1;
+        }
+        let put_place = moves.trailing_zeros() as i32;
+        assert!(board.put_piece(1 << put_place).is_ok());
+        reference.put(put_place / 8, put_place % 8);
+    }
+}
+
+#[test]
+fn random_games_match_reference() {
+    let mut rng = XorShiftRng::seed_from_u64(0);
+    for _ in 0..500 {
+        play_random_game(&mut rng);
+    }
+}
+
+#[test]
+#[ignore]
+fn random_games_match_reference_long() {
+    let mut rng = XorShiftRng::seed_from_u64(1);
+    for _ in 0..100_000 {
+        play_random_game(&mut rng);
+    }
+}